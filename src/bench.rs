@@ -0,0 +1,306 @@
+use std::collections::BTreeMap;
+use std::thread;
+use serde::Serialize;
+use crate::config::Config;
+use crate::game::{Game, Status};
+
+
+const DEFAULT_TICK_LIMIT: u64 = 100_000;
+
+
+/// How a single benchmark game went.
+#[derive(Clone, Serialize)]
+pub struct Outcome {
+    pub strategy: String,
+    pub seed: u64,
+    pub score: u32,
+    /// Blocks of snake at the end, head included
+    pub length: u32,
+    pub foods: u32,
+    pub ticks: u64,
+    /// What the snake crashed into, `timeout` when it was still alive at
+    /// the tick limit or `cleared` when the game reached its target or the
+    /// snake filled the board
+    pub cause: String,
+}
+
+/// The spread of one measure over a batch of games.
+#[derive(Clone, Serialize)]
+pub struct Distribution {
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    pub median: f64,
+    /// The value 90% of the games stay at or below
+    pub p90: u64,
+    pub std_dev: f64,
+}
+
+/// The results of one strategy over a batch of games.
+#[derive(Clone, Serialize)]
+pub struct Summary {
+    pub strategy: String,
+    pub games: usize,
+    pub score: Distribution,
+    pub length: Distribution,
+    pub ticks: Distribution,
+    /// How many games ended each way, see `Outcome::cause`
+    pub causes: BTreeMap<String, usize>,
+}
+
+/// Plays many headless games of one bot on the same board. Game `n` of
+/// the batch is seeded with the first seed plus `n`, so every game can be
+/// replayed on its own and two strategies run on the same seeds face the
+/// same food.
+pub struct Batch {
+    config: Config,
+    strategy: String,
+    games: usize,
+    first_seed: u64,
+    tick_limit: u64,
+    jobs: usize,
+}
+
+
+impl Summary {
+
+    /// Sums up `outcomes`, which should all come from `strategy`.
+    pub fn of(strategy: &str, outcomes: &[Outcome]) -> Summary {
+        let mut causes = BTreeMap::new();
+        for outcome in outcomes {
+            *causes.entry(outcome.cause.clone()).or_insert(0) += 1;
+        }
+        Summary {
+            strategy: String::from(strategy),
+            games: outcomes.len(),
+            score: Distribution::of(outcomes.iter()
+                    .map(|outcome| u64::from(outcome.score))),
+            length: Distribution::of(outcomes.iter()
+                    .map(|outcome| u64::from(outcome.length))),
+            ticks: Distribution::of(outcomes.iter()
+                    .map(|outcome| outcome.ticks)),
+            causes,
+        }
+    }
+
+}
+
+
+impl Distribution {
+
+    pub fn of<I: Iterator<Item = u64>>(values: I) -> Distribution {
+        let mut values: Vec<u64> = values.collect();
+        if values.is_empty() {
+            return Distribution {
+                min: 0, max: 0, mean: 0.0, median: 0.0, p90: 0, std_dev: 0.0,
+            };
+        }
+        values.sort_unstable();
+        let count = values.len();
+        let mean = values.iter().sum::<u64>() as f64 / count as f64;
+        let variance = values.iter()
+                .map(|value| (*value as f64 - mean).powi(2))
+                .sum::<f64>() / count as f64;
+        let median = if count % 2 == 0 {
+            (values[count / 2 - 1] + values[count / 2]) as f64 / 2.0
+        } else {
+            values[count / 2] as f64
+        };
+        Distribution {
+            min: values[0],
+            max: values[count - 1],
+            mean,
+            median,
+            p90: values[(count * 9).div_ceil(10) - 1],
+            std_dev: variance.sqrt(),
+        }
+    }
+
+}
+
+
+impl Batch {
+
+    /// A batch of `games` games of the bot called `strategy`, on the board
+    /// of `config` without its other snakes.
+    pub fn new(config: &Config, strategy: &str, games: usize) -> Batch {
+        let mut config = config.clone();
+        config.set_player_count(1);
+        config.snake.bot = Some(String::from(strategy));
+        Batch {
+            config,
+            strategy: String::from(strategy),
+            games,
+            first_seed: 0,
+            tick_limit: DEFAULT_TICK_LIMIT,
+            jobs: 1,
+        }
+    }
+
+    pub fn with_first_seed(mut self, first_seed: u64) -> Batch {
+        self.first_seed = first_seed;
+        self
+    }
+
+    /// Stops games whose snake is still alive after `tick_limit` ticks,
+    /// as a snake that never dies would otherwise play forever.
+    pub fn with_tick_limit(mut self, tick_limit: u64) -> Batch {
+        self.tick_limit = tick_limit;
+        self
+    }
+
+    /// Plays the games on `jobs` threads. The outcomes do not depend on
+    /// it.
+    pub fn with_jobs(mut self, jobs: usize) -> Batch {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Plays every game of the batch and returns their outcomes in the
+    /// order of their seeds.
+    pub fn run(&self) -> Vec<Outcome> {
+        let jobs = self.jobs.min(self.games).max(1);
+        let mut outcomes: Vec<(usize, Outcome)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..jobs)
+                    .map(|job| scope.spawn(move || {
+                        (job..self.games).step_by(jobs)
+                                .map(|index| (index, self.play(index)))
+                                .collect::<Vec<_>>()
+                    }))
+                    .collect();
+            workers.into_iter()
+                    .flat_map(|worker| worker.join().expect("benchmark game panicked"))
+                    .collect()
+        });
+        outcomes.sort_by_key(|(index, _)| *index);
+        outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+    }
+
+    /// Plays game `index` of the batch on its own.
+    pub fn play(&self, index: usize) -> Outcome {
+        let seed = self.first_seed.wrapping_add(index as u64);
+        let mut game = self.config.build_game_with_seed(seed);
+        while game.get_status() == &Status::Running
+                && game.get_ticks() < self.tick_limit {
+            game.tick();
+        }
+        self.outcome(&game)
+    }
+
+    pub fn get_strategy(&self) -> &str {
+        &self.strategy
+    }
+
+    pub fn get_games(&self) -> usize {
+        self.games
+    }
+
+    fn outcome(&self, game: &Game) -> Outcome {
+        let cause = match (game.get_status(), game.get_death(0)) {
+            (_, Some(death)) => death.to_string(),
+            (Status::Cleared, None) => String::from("cleared"),
+            _ => String::from("timeout"),
+        };
+        let snake = game.get_snake();
+        Outcome {
+            strategy: self.strategy.clone(),
+            seed: game.get_seed(),
+            score: game.get_score(),
+            length: snake.get_length(),
+            foods: snake.get_eatings(),
+            ticks: game.get_ticks(),
+            cause,
+        }
+    }
+
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn outcome(score: u32, ticks: u64, cause: &str) -> Outcome {
+        Outcome {
+            strategy: String::from("bfs"),
+            seed: 0,
+            score,
+            length: 3 + score / 10,
+            foods: score / 10,
+            ticks,
+            cause: String::from(cause),
+        }
+    }
+
+    #[test]
+    fn distribution_of_an_even_count() {
+        let distribution = Distribution::of((1..=10).rev());
+        assert_eq!(distribution.min, 1);
+        assert_eq!(distribution.max, 10);
+        assert_eq!(distribution.mean, 5.5);
+        assert_eq!(distribution.median, 5.5);
+        assert_eq!(distribution.p90, 9);
+        assert!((distribution.std_dev - 8.25_f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn distribution_of_an_odd_count() {
+        let distribution = Distribution::of([7, 1, 4].iter().cloned());
+        assert_eq!(distribution.median, 4.0);
+        assert_eq!(distribution.p90, 7);
+        assert_eq!(distribution.mean, 4.0);
+        let single = Distribution::of(Some(5).into_iter());
+        assert_eq!((single.min, single.max, single.p90), (5, 5, 5));
+        assert_eq!(single.std_dev, 0.0);
+    }
+
+    #[test]
+    fn distribution_of_nothing_is_all_zero() {
+        let distribution = Distribution::of(std::iter::empty());
+        assert_eq!((distribution.min, distribution.max, distribution.p90), (0, 0, 0));
+        assert_eq!((distribution.mean, distribution.median), (0.0, 0.0));
+    }
+
+    #[test]
+    fn summary_counts_games_and_causes() {
+        let outcomes = [
+            outcome(10, 100, "wall"),
+            outcome(30, 300, "self"),
+            outcome(20, 200, "wall"),
+            outcome(40, 1000, "timeout"),
+        ];
+        let summary = Summary::of("bfs", &outcomes);
+        assert_eq!(summary.strategy, "bfs");
+        assert_eq!(summary.games, 4);
+        assert_eq!(summary.score.mean, 25.0);
+        assert_eq!(summary.score.max, 40);
+        assert_eq!(summary.length.min, 4);
+        assert_eq!(summary.ticks.median, 250.0);
+        let causes: Vec<(&str, usize)> = summary.causes.iter()
+                .map(|(cause, count)| (cause.as_str(), *count))
+                .collect();
+        assert_eq!(causes, vec![("self", 1), ("timeout", 1), ("wall", 2)]);
+    }
+
+    #[test]
+    fn outcomes_do_not_depend_on_the_jobs() {
+        let batch = Batch::new(&Config::default(), "greedy", 5)
+                .with_first_seed(11)
+                .with_tick_limit(300);
+        let alone: Vec<String> = batch.run().iter()
+                .map(|outcome| format!("{} {} {} {}", outcome.seed, outcome.score,
+                        outcome.ticks, outcome.cause))
+                .collect();
+        let shared: Vec<String> = batch.with_jobs(3).run().iter()
+                .map(|outcome| format!("{} {} {} {}", outcome.seed, outcome.score,
+                        outcome.ticks, outcome.cause))
+                .collect();
+        assert_eq!(alone.len(), 5);
+        assert!(alone[0].starts_with("11 "));
+        assert_eq!(alone, shared);
+    }
+
+}
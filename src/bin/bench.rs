@@ -0,0 +1,157 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::thread;
+use clap::{Parser, ValueEnum};
+use clap::builder::PossibleValuesParser;
+use serde::Serialize;
+use snake::bench::{Batch, Outcome, Summary};
+use snake::cli::fail;
+use snake::config::{Config, Overrides};
+use snake::controller;
+
+
+/// Plays batches of headless games with bots and compares how they do.
+#[derive(Parser)]
+#[command(name = "snake-bench", version)]
+struct Args {
+    /// Bot strategy to run, repeat to compare several on the same seeds
+    #[arg(long = "bot", value_name = "STRATEGY", required = true,
+            value_parser = PossibleValuesParser::new(controller::strategy_names()))]
+    bots: Vec<String>,
+    /// Games per strategy
+    #[arg(long, default_value_t = 1000)]
+    games: usize,
+    /// Seed of the first game, the next games count up from it
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Ticks after which a game whose snake is still alive stops
+    #[arg(long, default_value_t = 100_000)]
+    tick_limit: u64,
+    /// Threads to play the games on, all cores by default
+    #[arg(long)]
+    jobs: Option<usize>,
+    /// Output: a summary table, or every game as CSV or JSON
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+    /// Write the output to a file instead of the standard output
+    #[arg(long, value_name = "FILE")]
+    output: Option<PathBuf>,
+    /// Config file to use instead of the default one
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Override a config setting, e.g. playground.width=50
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    overrides: Vec<String>,
+    /// Obstacle map: pillars, cross, rooms, tunnel or a level file
+    #[arg(long, value_name = "LEVEL")]
+    level: Option<String>,
+    /// No walls: snakes wrap around to the opposite edge
+    #[arg(long)]
+    wrap: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Table,
+    Csv,
+    Json,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    summaries: &'a [Summary],
+    games: &'a [Outcome],
+}
+
+
+fn main() {
+    let args = Args::parse();
+    let config = load_config(&args);
+    let jobs = args.jobs.unwrap_or_else(|| {
+        thread::available_parallelism().map(usize::from).unwrap_or(1)
+    });
+
+    let mut outcomes = Vec::new();
+    let mut summaries = Vec::new();
+    for bot in args.bots.iter() {
+        let batch = Batch::new(&config, bot, args.games)
+            .with_first_seed(args.seed)
+            .with_tick_limit(args.tick_limit)
+            .with_jobs(jobs);
+        let batch_outcomes = batch.run();
+        summaries.push(Summary::of(bot, &batch_outcomes));
+        outcomes.extend(batch_outcomes);
+    }
+
+    let result = match &args.output {
+        Some(path) => File::create(path)
+            .and_then(|file| write_report(BufWriter::new(file),
+                    args.format, &summaries, &outcomes)),
+        None => write_report(io::stdout().lock(),
+                args.format, &summaries, &outcomes),
+    };
+    if let Err(e) = result {
+        fail(1, &format!("Failed to write the results: {}", e));
+    }
+}
+
+fn load_config(args: &Args) -> Config {
+    let overrides = Overrides {
+        settings: args.overrides.clone(),
+        level: args.level.clone(),
+        wrap: args.wrap,
+        ..Overrides::default()
+    };
+    Config::load_with_overrides(args.config.as_deref(), &overrides)
+            .unwrap_or_else(|e| fail(e.get_exit_code(), &e.to_string()))
+}
+
+fn write_report<W: Write>(mut out: W, format: Format, summaries: &[Summary],
+        outcomes: &[Outcome]) -> io::Result<()> {
+    match format {
+        Format::Table => write_table(&mut out, summaries)?,
+        Format::Csv => write_csv(&mut out, outcomes)?,
+        Format::Json => {
+            let report = Report { summaries, games: outcomes };
+            serde_json::to_writer_pretty(&mut out, &report)?;
+            writeln!(out)?;
+        },
+    }
+    out.flush()
+}
+
+fn write_table<W: Write>(out: &mut W, summaries: &[Summary]) -> io::Result<()> {
+    for summary in summaries {
+        writeln!(out, "{} ({} games)", summary.strategy, summary.games)?;
+        writeln!(out, "  {:<8}{:>10}{:>10}{:>10}{:>10}{:>10}{:>10}",
+                "", "min", "median", "mean", "p90", "max", "std dev")?;
+        let measures = [
+            ("score", &summary.score),
+            ("length", &summary.length),
+            ("ticks", &summary.ticks),
+        ];
+        for (name, distribution) in measures.iter() {
+            writeln!(out, "  {:<8}{:>10}{:>10.1}{:>10.1}{:>10}{:>10}{:>10.1}",
+                    name, distribution.min, distribution.median,
+                    distribution.mean, distribution.p90, distribution.max,
+                    distribution.std_dev)?;
+        }
+        let causes: Vec<String> = summary.causes.iter()
+                .map(|(cause, count)| format!("{} {}", cause, count))
+                .collect();
+        writeln!(out, "  ended by: {}", causes.join(", "))?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn write_csv<W: Write>(out: &mut W, outcomes: &[Outcome]) -> io::Result<()> {
+    writeln!(out, "strategy,seed,score,length,foods,ticks,cause")?;
+    for outcome in outcomes {
+        writeln!(out, "{},{},{},{},{},{},{}",
+                outcome.strategy, outcome.seed, outcome.score, outcome.length,
+                outcome.foods, outcome.ticks, outcome.cause)?;
+    }
+    Ok(())
+}
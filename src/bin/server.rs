@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+use clap::Parser;
+use snake::cli::fail;
+use snake::config::{Config, Overrides, MAX_PLAYERS};
+use snake::net::DEFAULT_ADDRESS;
+use snake::server::{Server, TICK_RATE};
+
+
+/// Hosts networked games of rusty snake.
+#[derive(Parser)]
+#[command(name = "snake-server", version)]
+struct Args {
+    /// Address to listen on
+    #[arg(long, default_value = DEFAULT_ADDRESS)]
+    address: String,
+    /// Config file to use instead of the default one
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Override a config setting, e.g. playground.width=50
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    overrides: Vec<String>,
+    /// Obstacle map: pillars, cross, rooms, tunnel or a level file
+    #[arg(long, value_name = "LEVEL")]
+    level: Option<String>,
+    /// No walls: snakes wrap around to the opposite edge
+    #[arg(long)]
+    wrap: bool,
+    /// Seats in the lobby
+    #[arg(long, default_value_t = MAX_PLAYERS,
+            value_parser = clap::value_parser!(u8).range(1..=i64::from(MAX_PLAYERS)))]
+    max_players: u8,
+    /// Server updates per second
+    #[arg(long, default_value_t = TICK_RATE,
+            value_parser = clap::value_parser!(u32).range(1..=1000))]
+    tick_rate: u32,
+}
+
+
+fn main() {
+    let args = Args::parse();
+    let config = load_config(&args);
+    let server = Server::bind(&args.address, config).unwrap_or_else(|e| {
+        fail(1, &format!("Failed to listen on {}: {}", args.address, e))
+    });
+    println!("Listening on {}", args.address);
+    server
+        .with_max_players(usize::from(args.max_players))
+        .with_tick_rate(args.tick_rate)
+        .run();
+}
+
+fn load_config(args: &Args) -> Config {
+    let overrides = Overrides {
+        settings: args.overrides.clone(),
+        level: args.level.clone(),
+        wrap: args.wrap,
+        ..Overrides::default()
+    };
+    let mut config = Config::load_with_overrides(args.config.as_deref(), &overrides)
+            .unwrap_or_else(|e| fail(e.get_exit_code(), &e.to_string()));
+    // clients get the board laid out already
+    config.level = None;
+    config
+}
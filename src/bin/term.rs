@@ -0,0 +1,227 @@
+use std::io;
+use std::time::{Duration, Instant};
+use clap::Parser;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use snake::campaign::Campaign;
+use snake::cli::{fail, GameArgs};
+use snake::config::{Config, Overrides};
+use snake::game::{Game, Status};
+use snake::input::{Action, Controls};
+use snake::replay::Replay;
+use snake::terminal::Screen;
+use snake::theme::Theme;
+
+
+// how long to wait for a key before moving the game on
+const FRAME: Duration = Duration::from_millis(10);
+
+
+/// The classic snake game, played in a terminal.
+#[derive(Parser)]
+#[command(name = "snake-term", version)]
+struct Args {
+    #[command(flatten)]
+    game: GameArgs,
+    /// Colors of the game: classic, night, an installed theme pack or a
+    /// theme manifest
+    #[arg(long, value_name = "THEME")]
+    theme: Option<String>,
+}
+
+
+fn main() {
+    let args = Args::parse();
+    let config = load_config(&args);
+    let campaign = args.game.mode.load_campaign();
+    let game = build_game(&config, campaign.as_ref(), args.game.seed);
+    let theme = load_theme(config.theme.as_deref());
+    let result = Screen::open().and_then(|mut screen| {
+        play(&mut screen, game, &config, campaign, theme, &args)
+    });
+    if let Err(e) = result {
+        fail(1, &format!("Terminal error: {}", e));
+    }
+}
+
+fn load_config(args: &Args) -> Config {
+    let overrides = Overrides {
+        theme: args.theme.clone(),
+        ..args.game.get_overrides(args.game.mode)
+    };
+    Config::load_with_overrides(args.game.config.as_deref(), &overrides)
+            .unwrap_or_else(|e| fail(e.get_exit_code(), &e.to_string()))
+}
+
+fn build_game(config: &Config, campaign: Option<&Campaign>, seed: Option<u64>)
+        -> Game {
+    Game::from_config(config, campaign, seed)
+            .unwrap_or_else(|e| fail(1, &format!("Invalid {}", e)))
+}
+
+fn load_theme(theme: Option<&str>) -> Theme {
+    match theme {
+        Some(name) => Theme::find(name).unwrap_or_else(|e| {
+            fail(1, &format!("Failed to load theme {}: {}", name, e))
+        }),
+        None => Theme::classic(),
+    }
+}
+
+fn play(screen: &mut Screen, mut game: Game, config: &Config,
+        mut campaign: Option<Campaign>, mut theme: Theme, args: &Args)
+        -> io::Result<()> {
+    let controls = &config.controls;
+    let mut replay = Replay::record(&game).with_campaign(campaign.as_ref());
+    let mut round_over = false;
+    let mut last_update = Instant::now();
+    let mut redraw = true;
+    loop {
+        if event::poll(FRAME)? {
+            match event::read()? {
+                Event::Key(KeyEvent { code, modifiers, kind: KeyEventKind::Press, .. }) => {
+                    redraw = true;
+                    if code == KeyCode::Char('c') && modifiers.contains(KeyModifiers::CONTROL) {
+                        return Ok(());
+                    }
+                    let action = button_names(code).iter()
+                            .find_map(|name| controls.get_action(name));
+                    match (action, game.get_status()) {
+                        (Some(Action::Quit), _) => return Ok(()),
+                        (Some(Action::Pause), _) => game.toggle_pause(),
+                        (Some(Action::Step), Status::Paused) => game.single_step(),
+                        (Some(Action::NextTheme), _) => {
+                            let next = theme.get_next_name();
+                            match Theme::find(&next) {
+                                Ok(next) => theme = next,
+                                Err(e) => eprintln!("Failed to load theme {}: {}", next, e),
+                            }
+                        },
+                        (Some(Action::Restart), Status::Cleared) => {
+                            match campaign.as_mut() {
+                                Some(campaign) => {
+                                    if campaign.is_complete() {
+                                        campaign.start_over();
+                                    }
+                                    game = build_game(config, Some(campaign), None);
+                                    screen.clear()?;
+                                },
+                                None => game.restart(),
+                            }
+                            replay = Replay::record(&game)
+                                    .with_campaign(campaign.as_ref());
+                            round_over = false;
+                        },
+                        (Some(Action::Restart), _) => {
+                            game.restart();
+                            replay = Replay::record(&game)
+                                    .with_campaign(campaign.as_ref());
+                            round_over = false;
+                        },
+                        (Some(turn), _) => {
+                            if let Some((player, direction)) = turn.get_turn(&game) {
+                                replay.record_turn(&game, player, direction.clone());
+                                game.turn_snake(player, direction);
+                            }
+                        },
+                        (None, _) => (),
+                    }
+                },
+                Event::Resize(_, _) => {
+                    screen.clear()?;
+                    redraw = true;
+                },
+                _ => (),
+            }
+        }
+
+        let now = Instant::now();
+        let ticks = game.get_ticks();
+        game.update(now.duration_since(last_update).as_secs_f64());
+        last_update = now;
+        redraw |= game.get_ticks() != ticks;
+
+        if game.is_finished() && !round_over {
+            round_over = true;
+            if let Some(path) = &args.game.record {
+                replay.finish(&game);
+                if let Err(e) = replay.save(path) {
+                    eprintln!("Failed to save replay to {}: {}",
+                            path.display(), e);
+                }
+            }
+            if let (Status::Cleared, Some(campaign)) =
+                    (game.get_status(), campaign.as_mut()) {
+                campaign.advance();
+                if let Err(e) = campaign.save() {
+                    eprintln!("Failed to save campaign progress: {}", e);
+                }
+            }
+        }
+        if redraw {
+            redraw = false;
+            let lines = hint_lines(&game, controls, campaign.as_ref());
+            let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+            screen.draw(&game, &theme, &lines)?;
+        }
+    }
+}
+
+// what to do next, under the banner of a paused or finished game
+fn hint_lines(game: &Game, controls: &Controls, campaign: Option<&Campaign>)
+        -> Vec<String> {
+    let button = |buttons: &[String]| buttons.first().cloned()
+            .unwrap_or_else(|| String::from("none"));
+    let (restart, quit) = (button(&controls.restart), button(&controls.quit));
+    match (game.get_status(), campaign) {
+        (Status::Running, _) => Vec::new(),
+        (Status::Paused, _) => vec![format!("{}: resume  {}: step  {}: quit",
+                button(&controls.pause), button(&controls.step), quit)],
+        (Status::GameOver, _) => vec![format!("{}: play again  {}: quit", restart, quit)],
+        (Status::Cleared, Some(campaign)) if !campaign.is_complete() => {
+            let stage = campaign.get_stage();
+            vec![
+                format!("Next: stage {} of {}, {}", campaign.get_stage_number(),
+                        campaign.get_stage_count(), stage.get_name()),
+                format!("Goal: {}", stage.get_target()),
+                format!("{}: go on  {}: quit", restart, quit),
+            ]
+        },
+        (Status::Cleared, Some(_)) => vec![
+            String::from("You beat the whole campaign!"),
+            format!("{}: start over  {}: quit", restart, quit),
+        ],
+        (Status::Cleared, None) => vec![format!("{}: play again  {}: quit", restart, quit)],
+    }
+}
+
+/// The names the window gives `key`, to look it up in the controls.
+/// Keypads send plain digits in a terminal, so a digit stands for its key
+/// on the keypad first, then for the one above the letters.
+fn button_names(key: KeyCode) -> Vec<String> {
+    let name = match key {
+        KeyCode::Up => "Up",
+        KeyCode::Down => "Down",
+        KeyCode::Left => "Left",
+        KeyCode::Right => "Right",
+        KeyCode::Enter => "Return",
+        KeyCode::Esc => "Escape",
+        KeyCode::Backspace => "Backspace",
+        KeyCode::Tab => "Tab",
+        KeyCode::Home => "Home",
+        KeyCode::End => "End",
+        KeyCode::PageUp => "PageUp",
+        KeyCode::PageDown => "PageDown",
+        KeyCode::Insert => "Insert",
+        KeyCode::Delete => "Delete",
+        KeyCode::F(number) => return vec![format!("F{}", number)],
+        KeyCode::Char(' ') => "Space",
+        KeyCode::Char(c) if c.is_ascii_digit() => {
+            return vec![format!("NumPad{}", c), format!("D{}", c)];
+        },
+        KeyCode::Char(c) if c.is_ascii_alphabetic() => {
+            return vec![c.to_ascii_uppercase().to_string()];
+        },
+        _ => return Vec::new(),
+    };
+    vec![String::from(name)]
+}
@@ -0,0 +1,362 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::config::{Config, ConfigError};
+use crate::game::Game;
+
+
+const PROGRESS_MAGIC: &str = "rusty-snake-campaign";
+const PROGRESS_VERSION: u32 = 1;
+const PROGRESS_FILE: &str = "campaign.txt";
+
+const BUILTIN_STAGES: [(&str, Option<&str>, f64, Target); 5] = [
+    ("Meadow", None, 0.30, Target::Foods(5)),
+    ("Pillars", Some("pillars"), 0.26, Target::Score(30)),
+    ("Cross", Some("cross"), 0.22, Target::Length(15)),
+    ("Rooms", Some("rooms"), 0.19, Target::Score(60)),
+    ("Tunnel", Some("tunnel"), 0.16, Target::Foods(20)),
+];
+
+
+/// What a round has to reach to clear a campaign stage.
+#[derive(Clone, PartialEq)]
+pub enum Target {
+    Score(u32),
+    /// Blocks of snake, head included
+    Length(u32),
+    /// Foods and bonuses eaten
+    Foods(u32),
+}
+
+/// One board of the campaign: an optional level laid over the base
+/// config, the starting speed and the target to reach.
+#[derive(Clone)]
+pub struct Stage {
+    name: String,
+    level: Option<String>,
+    move_delay: f64,
+    target: Target,
+}
+
+/// The successive stages of the campaign and how far the player got,
+/// saved next to the high scores.
+pub struct Campaign {
+    stages: Vec<Stage>,
+    current: usize,
+    /// The last stage was cleared
+    complete: bool,
+    path: Option<PathBuf>,
+}
+
+
+impl Target {
+
+    pub fn is_reached(&self, game: &Game) -> bool {
+        self.progress(game) >= self.get_goal()
+    }
+
+    pub fn progress(&self, game: &Game) -> u32 {
+        match self {
+            Target::Score(_) => game.get_score(),
+            Target::Length(_) => game.get_snake().get_length(),
+            Target::Foods(_) => game.get_snake().get_eatings(),
+        }
+    }
+
+    pub fn get_goal(&self) -> u32 {
+        match self {
+            Target::Score(goal)
+                    | Target::Length(goal)
+                    | Target::Foods(goal) => *goal,
+        }
+    }
+
+    pub fn get_label(&self) -> &str {
+        match self {
+            Target::Score(_) => "Score",
+            Target::Length(_) => "Length",
+            Target::Foods(_) => "Foods",
+        }
+    }
+
+}
+
+
+impl fmt::Display for Target {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Target::Score(goal) => write!(f, "score {} points", goal),
+            Target::Length(goal) => write!(f, "grow to {} blocks", goal),
+            Target::Foods(goal) => write!(f, "eat {} foods", goal),
+        }
+    }
+
+}
+
+
+impl Stage {
+
+    pub fn new(name: &str, level: Option<&str>, move_delay: f64,
+            target: Target) -> Stage {
+        Stage {
+            name: String::from(name),
+            level: level.map(String::from),
+            move_delay,
+            target,
+        }
+    }
+
+    /// The settings of this stage: `base` with the stage's level and
+    /// starting speed on top.
+    pub fn build_config(&self, base: &Config) -> Result<Config, ConfigError> {
+        let mut config = base.clone();
+        if self.level.is_some() {
+            config.level = self.level.clone();
+            config.apply_level()?;
+        }
+        config.timing.move_delay = self.move_delay;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_level(&self) -> Option<&str> {
+        self.level.as_deref()
+    }
+
+    pub fn get_move_delay(&self) -> f64 {
+        self.move_delay
+    }
+
+    pub fn get_target(&self) -> &Target {
+        &self.target
+    }
+
+}
+
+
+impl Default for Campaign {
+
+    fn default() -> Campaign {
+        match Config::data_path(PROGRESS_FILE) {
+            Some(path) => Campaign::load(path),
+            None => Campaign::new(None),
+        }
+    }
+
+}
+
+
+impl Campaign {
+
+    /// The built-in campaign, starting from its first stage.
+    pub fn new(path: Option<PathBuf>) -> Campaign {
+        let stages = BUILTIN_STAGES.iter()
+                .map(|(name, level, move_delay, target)| {
+                    Stage::new(name, *level, *move_delay, target.clone())
+                })
+                .collect();
+        Campaign {
+            stages,
+            current: 0,
+            complete: false,
+            path,
+        }
+    }
+
+    /// Resumes the progress stored at `path`. Like the high scores, a
+    /// missing or corrupted file starts the campaign over instead of
+    /// failing.
+    pub fn load<P: AsRef<Path>>(path: P) -> Campaign {
+        let path = path.as_ref();
+        let mut campaign = Campaign::new(Some(path.to_path_buf()));
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                return campaign;
+            },
+            Err(e) => {
+                eprintln!("Ignoring unreadable campaign progress {}: {}",
+                        path.display(), e);
+                return campaign;
+            },
+        };
+
+        let mut lines = text.lines().map(str::trim);
+        let header = format!("{} {}", PROGRESS_MAGIC, PROGRESS_VERSION);
+        let stage = match (lines.next(), lines.next()) {
+            (Some(first), Some(second)) if first == header => second
+                    .strip_prefix("stage ")
+                    .and_then(|stage| stage.parse::<usize>().ok()),
+            _ => None,
+        };
+        let complete = match lines.next() {
+            None | Some("") => Some(false),
+            Some("complete") => Some(true),
+            Some(_) => None,
+        };
+        match (stage, complete) {
+            (Some(stage), Some(complete)) if stage < campaign.stages.len() => {
+                campaign.current = stage;
+                campaign.complete = complete;
+            },
+            _ => eprintln!("Ignoring corrupted campaign progress {}",
+                    path.display()),
+        }
+        campaign
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder)?;
+        }
+        let mut text = format!("{} {}\nstage {}\n",
+                PROGRESS_MAGIC, PROGRESS_VERSION, self.current);
+        if self.complete {
+            text.push_str("complete\n");
+        }
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, text)?;
+        fs::rename(&temp_path, path)
+    }
+
+    /// Builds the game of the current stage on top of `base`.
+    pub fn build_game(&self, base: &Config) -> Result<Game, ConfigError> {
+        let mut game = self.get_stage().build_config(base)?.build_game();
+        game.set_target(Some(self.get_stage().get_target().clone()));
+        Ok(game)
+    }
+
+    pub fn build_game_with_seed(&self, base: &Config, seed: u64)
+            -> Result<Game, ConfigError> {
+        let mut game = self.get_stage().build_config(base)?
+                .build_game_with_seed(seed);
+        game.set_target(Some(self.get_stage().get_target().clone()));
+        Ok(game)
+    }
+
+    /// Moves on to the next stage, or marks the campaign complete once
+    /// the last one is cleared.
+    pub fn advance(&mut self) {
+        if self.current + 1 < self.stages.len() {
+            self.current += 1;
+        } else {
+            self.complete = true;
+        }
+    }
+
+    /// Goes back to the first stage, e.g. to play a complete campaign
+    /// again.
+    pub fn start_over(&mut self) {
+        self.current = 0;
+        self.complete = false;
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    pub fn get_stage(&self) -> &Stage {
+        &self.stages[self.current]
+    }
+
+    /// The current stage counted from 1, as shown to the player.
+    pub fn get_stage_number(&self) -> usize {
+        self.current + 1
+    }
+
+    pub fn get_stage_count(&self) -> usize {
+        self.stages.len()
+    }
+
+    pub fn get_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::env;
+    use std::process;
+
+    // a progress file of its own for each test, as tests run in parallel
+    fn progress_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("snake-campaign-{}-{}.txt", name, process::id()))
+    }
+
+    #[test]
+    fn advance_goes_through_every_stage_then_completes() {
+        let mut campaign = Campaign::new(None);
+        for number in 1..campaign.get_stage_count() {
+            assert_eq!(campaign.get_stage_number(), number);
+            campaign.advance();
+            assert!(!campaign.is_complete());
+        }
+        let last = campaign.get_stage_number();
+        assert_eq!(last, campaign.get_stage_count());
+        campaign.advance();
+        assert!(campaign.is_complete());
+        assert_eq!(campaign.get_stage_number(), last);
+        campaign.advance();
+        assert_eq!(campaign.get_stage_number(), last);
+        campaign.start_over();
+        assert!(!campaign.is_complete());
+        assert_eq!(campaign.get_stage_number(), 1);
+    }
+
+    #[test]
+    fn progress_is_saved_and_loaded() {
+        let path = progress_path("saved");
+        let mut campaign = Campaign::new(Some(path.clone()));
+        campaign.advance();
+        campaign.advance();
+        campaign.save().unwrap();
+        let loaded = Campaign::load(&path);
+        assert_eq!(loaded.get_stage_number(), 3);
+        assert!(!loaded.is_complete());
+
+        for _ in 0..campaign.get_stage_count() {
+            campaign.advance();
+        }
+        campaign.save().unwrap();
+        let loaded = Campaign::load(&path);
+        assert!(loaded.is_complete());
+        assert_eq!(loaded.get_stage_number(), loaded.get_stage_count());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn missing_or_corrupted_progress_starts_over() {
+        let path = progress_path("corrupted");
+        assert_eq!(Campaign::load(&path).get_stage_number(), 1);
+        let header = format!("{} {}", PROGRESS_MAGIC, PROGRESS_VERSION);
+        for text in [
+            String::from("stage 2\n"),
+            format!("{}\nstage two\n", header),
+            format!("{}\nstage 99\n", header),
+            format!("{}\nstage 2\ndone\n", header),
+            format!("{} 0\nstage 2\n", PROGRESS_MAGIC),
+        ].iter() {
+            fs::write(&path, text).unwrap();
+            let loaded = Campaign::load(&path);
+            assert_eq!(loaded.get_stage_number(), 1, "{}", text);
+            assert!(!loaded.is_complete());
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+}
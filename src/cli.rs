@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+use std::process;
+use clap::ValueEnum;
+use clap::builder::PossibleValuesParser;
+use crate::campaign::Campaign;
+use crate::config::{Overrides, MAX_PLAYERS};
+use crate::controller;
+
+
+/// Rules to play by.
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum Mode {
+    /// Walled board, one snake
+    Classic,
+    /// No walls: the snake wraps around to the opposite edge
+    Wrap,
+    /// Successive boards, each cleared by reaching its target
+    Campaign,
+}
+
+/// The options of a round, the same for every frontend.
+#[derive(clap::Args)]
+pub struct GameArgs {
+    /// Board width in blocks, border included
+    #[arg(long)]
+    pub width: Option<u32>,
+    /// Board height in blocks, border included
+    #[arg(long)]
+    pub height: Option<u32>,
+    /// Starting speed in steps per second
+    #[arg(long, value_parser = parse_positive)]
+    pub speed: Option<f64>,
+    /// Seed for food placement, to replay the same game
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Rules to play by
+    #[arg(long, value_enum, default_value_t = Mode::Classic)]
+    pub mode: Mode,
+    /// Snakes sharing the keyboard: arrows, WASD, IJKL and the keypad
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=i64::from(MAX_PLAYERS)))]
+    pub players: Option<u8>,
+    /// Add a snake steered by STRATEGY: greedy, bfs or hamilton
+    #[arg(long = "bot", value_name = "STRATEGY",
+            value_parser = PossibleValuesParser::new(controller::strategy_names()))]
+    pub bots: Vec<String>,
+    /// Obstacle map: pillars, cross, rooms, tunnel or a level file
+    #[arg(long, value_name = "LEVEL", conflicts_with_all = ["width", "height"])]
+    pub level: Option<String>,
+    /// Config file to use instead of the default one
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Override a config setting, e.g. playground.color=[0, 0, 1, 1]
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+    /// Save a replay of each finished round to FILE
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+}
+
+
+impl Mode {
+
+    /// The campaign a round of this mode plays through, from the saved
+    /// progress, or from the first stage again once it is complete.
+    pub fn load_campaign(self) -> Option<Campaign> {
+        match self {
+            Mode::Campaign => {
+                let mut campaign = Campaign::default();
+                if campaign.is_complete() {
+                    campaign.start_over();
+                }
+                Some(campaign)
+            },
+            Mode::Classic | Mode::Wrap => None,
+        }
+    }
+
+}
+
+
+impl GameArgs {
+
+    /// What these options change in the config file for a round of
+    /// `mode`.
+    pub fn get_overrides(&self, mode: Mode) -> Overrides {
+        Overrides {
+            settings: self.overrides.clone(),
+            campaign: mode == Mode::Campaign,
+            level: self.level.clone(),
+            width: self.width,
+            height: self.height,
+            speed: self.speed,
+            players: self.players.map(usize::from),
+            bots: self.bots.clone(),
+            wrap: mode == Mode::Wrap,
+            ..Overrides::default()
+        }
+    }
+
+}
+
+
+
+/// Parses a strictly positive, finite number, e.g. a speed or a scale
+/// given on the command line.
+pub fn parse_positive(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(number) if number > 0.0 && number.is_finite() => Ok(number),
+        _ => Err(String::from("expected a positive number")),
+    }
+}
+
+/// Prints `message` to the standard error and ends the program with
+/// `code`.
+pub fn fail(code: i32, message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(code);
+}
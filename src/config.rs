@@ -0,0 +1,796 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
+use crate::controller;
+use crate::food::Food;
+use crate::game::Game;
+use crate::input::Controls;
+use crate::grid::{Block, Shape, Position, Direction, Color};
+use crate::level::Level;
+use crate::playground::Playground;
+use crate::snake::Snake;
+
+
+const PLAYGROUND_WIDTH: u32 = 35;
+const PLAYGROUND_HEIGHT: u32 = 25;
+const PLAYGROUND_COLOR: Color = [0.66, 0.78, 0.22, 1.0];
+const BORDER_COLOR: Color = [0.80, 0.30, 0.30, 1.0];
+const BORDER_WIDTH: u32 = 1;
+const SNAKE_COLOR: Color = [0.19, 0.19, 0.18, 1.0];
+const RIVAL_COLORS: [Color; 3] = [
+    [0.20, 0.30, 0.75, 1.0],
+    [0.55, 0.20, 0.60, 1.0],
+    [0.90, 0.55, 0.10, 1.0],
+];
+const SNAKE_INIT_POSITION: Position = Position (5, 3);
+const SNAKE_INIT_DIRECTION: Direction = Direction::Right;
+const SNAKE_INIT_LENGTH: u32 = 2;
+const MOVE_DELAY: f64 = 0.3; //300ms
+const FOOD_CALORIES: u32 = 2;
+const BONUS_CALORIES: u32 = 10;
+const BONUS_DISAPPEAR_TIME: f64 = 5.0;
+const CONFIG_FOLDER: &str = "rusty-snake";
+const CONFIG_FILE: &str = "snake.toml";
+pub const MAX_PLAYERS: u8 = 4;
+
+
+/// Every tunable of a game. Missing fields in a config file fall back to
+/// the defaults below.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// A built-in level name or a level file laid over the playground
+    pub level: Option<String>,
+    /// A built-in theme name, an installed theme pack or a theme manifest
+    pub theme: Option<String>,
+    /// Glide the snakes between blocks, drawn up to a step behind the
+    /// game
+    pub smooth: bool,
+    pub playground: PlaygroundConfig,
+    pub snake: SnakeConfig,
+    /// Snakes of the second player and up, for local multiplayer
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rivals: Vec<SnakeConfig>,
+    pub food: FoodConfig,
+    pub bonus: BonusConfig,
+    pub timing: TimingConfig,
+    /// The buttons of each player and of the game
+    pub controls: Controls,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlaygroundConfig {
+    pub width: u32,
+    pub height: u32,
+    pub border_width: u32,
+    pub wrap: bool,
+    pub color: Color,
+    pub border_color: Color,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub walls: Vec<Position>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub food_zone: Vec<Position>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnakeConfig {
+    pub init_position: Position,
+    pub init_direction: Direction,
+    pub init_length: u32,
+    pub color: Color,
+    /// A strategy steering the snake instead of a player: greedy, bfs or
+    /// hamilton
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bot: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FoodConfig {
+    pub calories: u32,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BonusConfig {
+    pub calories: u32,
+    pub disappear_after: f64,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimingConfig {
+    pub move_delay: f64,
+}
+
+/// What a command line changes in the config file, applied over it in
+/// the order below.
+#[derive(Clone, Default)]
+pub struct Overrides {
+    /// `section.key=value` assignments, as for `Config::set`
+    pub settings: Vec<String>,
+    /// The round is a campaign, which picks its own levels
+    pub campaign: bool,
+    pub level: Option<String>,
+    pub theme: Option<String>,
+    pub smooth: bool,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Steps per second at the start of a round
+    pub speed: Option<f64>,
+    /// Snakes steered from the keyboard, one when only bots are given
+    pub players: Option<usize>,
+    /// Strategies of the snakes after the players'
+    pub bots: Vec<String>,
+    pub wrap: bool,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(String),
+    Invalid(String),
+}
+
+/// Why the settings of a command line could not be put together.
+#[derive(Debug)]
+pub enum OverrideError {
+    /// The config file could not be read
+    Load(PathBuf, ConfigError),
+    /// A command line setting was refused
+    Setting(String),
+    Level(ConfigError),
+    /// The settings make no playable game
+    Invalid(ConfigError),
+}
+
+
+impl Default for PlaygroundConfig {
+
+    fn default() -> PlaygroundConfig {
+        PlaygroundConfig {
+            width: PLAYGROUND_WIDTH,
+            height: PLAYGROUND_HEIGHT,
+            border_width: BORDER_WIDTH,
+            wrap: false,
+            color: PLAYGROUND_COLOR,
+            border_color: BORDER_COLOR,
+            walls: Vec::new(),
+            food_zone: Vec::new(),
+        }
+    }
+
+}
+
+
+impl Default for SnakeConfig {
+
+    fn default() -> SnakeConfig {
+        SnakeConfig {
+            init_position: SNAKE_INIT_POSITION,
+            init_direction: SNAKE_INIT_DIRECTION,
+            init_length: SNAKE_INIT_LENGTH,
+            color: SNAKE_COLOR,
+            bot: None,
+        }
+    }
+
+}
+
+
+impl Default for FoodConfig {
+
+    fn default() -> FoodConfig {
+        FoodConfig {
+            calories: FOOD_CALORIES,
+        }
+    }
+
+}
+
+
+impl Default for BonusConfig {
+
+    fn default() -> BonusConfig {
+        BonusConfig {
+            calories: BONUS_CALORIES,
+            disappear_after: BONUS_DISAPPEAR_TIME,
+        }
+    }
+
+}
+
+
+impl Default for TimingConfig {
+
+    fn default() -> TimingConfig {
+        TimingConfig {
+            move_delay: MOVE_DELAY,
+        }
+    }
+
+}
+
+
+impl Config {
+
+    /// Where the config file is looked up when none is given explicitly.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir()
+                .map(|folder| folder.join(CONFIG_FOLDER).join(CONFIG_FILE))
+    }
+
+    /// Where a data file such as the high score table is kept.
+    pub fn data_path(file: &str) -> Option<PathBuf> {
+        dirs::data_dir()
+                .map(|folder| folder.join(CONFIG_FOLDER).join(file))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+        let text = fs::read_to_string(path)?;
+        Config::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        toml::from_str(text).map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+
+    /// Writes the settings to `path` in the config file format, creating
+    /// its folder when needed.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ConfigError> {
+        let path = path.as_ref();
+        let text = toml::to_string_pretty(self)
+                .map_err(|e| ConfigError::Parse(e.to_string()))?;
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder)?;
+        }
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, text)?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    /// The config file at `path`, or else the one at the default path,
+    /// or the defaults when there is none.
+    pub fn load_or_default(path: Option<&Path>) -> Result<Config, OverrideError> {
+        let path = path.map(Path::to_path_buf).or_else(|| {
+            Config::default_path().filter(|path| path.exists())
+        });
+        match path {
+            Some(path) => Config::load(&path)
+                    .map_err(|e| OverrideError::Load(path, e)),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// The config file as `load_or_default` finds it, with `overrides`
+    /// applied.
+    pub fn load_with_overrides(path: Option<&Path>, overrides: &Overrides)
+            -> Result<Config, OverrideError> {
+        let mut config = Config::load_or_default(path)?;
+        config.apply_overrides(overrides)?;
+        Ok(config)
+    }
+
+    /// Applies `overrides` and checks the result is playable.
+    pub fn apply_overrides(&mut self, overrides: &Overrides)
+            -> Result<(), OverrideError> {
+        for assignment in overrides.settings.iter() {
+            self.set(assignment).map_err(|e| {
+                OverrideError::Setting(format!("Invalid setting {}: {}", assignment, e))
+            })?;
+        }
+        if overrides.campaign && overrides.level.is_some() {
+            return Err(OverrideError::Setting(String::from(
+                    "The campaign picks its own levels, --level cannot be used")));
+        }
+        if let Some(level) = &overrides.level {
+            self.level = Some(level.clone());
+        }
+        self.apply_level().map_err(OverrideError::Level)?;
+        if let Some(theme) = &overrides.theme {
+            self.theme = Some(theme.clone());
+        }
+        if overrides.smooth {
+            self.smooth = true;
+        }
+        if let Some(width) = overrides.width {
+            self.playground.width = width;
+        }
+        if let Some(height) = overrides.height {
+            self.playground.height = height;
+        }
+        if let Some(speed) = overrides.speed {
+            self.timing.move_delay = 1.0 / speed;
+        }
+        if overrides.players.is_some() || !overrides.bots.is_empty() {
+            let players = overrides.players.unwrap_or(1);
+            let count = players + overrides.bots.len();
+            if count == 0 || count > usize::from(MAX_PLAYERS) {
+                return Err(OverrideError::Setting(
+                        format!("Between 1 and {} snakes can play", MAX_PLAYERS)));
+            }
+            self.set_player_count(count);
+            for (player, bot) in (players..).zip(overrides.bots.iter()) {
+                if let Some(snake) = self.get_snake_mut(player) {
+                    snake.bot = Some(bot.clone());
+                }
+            }
+        }
+        if overrides.wrap {
+            self.playground.wrap = true;
+        }
+        self.validate().map_err(OverrideError::Invalid)
+    }
+
+    /// Overrides a single setting given as `section.key=value`, where the
+    /// value is written as in the config file, e.g.
+    /// `playground.width=40` or `snake.init_direction="up"`.
+    pub fn set(&mut self, assignment: &str) -> Result<(), ConfigError> {
+        let mut parts = assignment.splitn(2, '=');
+        let key = parts.next().unwrap_or("").trim();
+        let value = parts.next().ok_or_else(|| ConfigError::Parse(
+                format!("expected key=value, got '{}'", assignment)))?;
+        let value: toml::Value = toml::from_str(&format!("value = {}", value))
+                .ok()
+                .and_then(|mut table: toml::value::Table| table.remove("value"))
+                // bare words are taken as strings, e.g. snake.init_direction=up
+                .unwrap_or_else(|| toml::Value::String(value.trim().to_string()));
+
+        let mut root = toml::Value::try_from(&*self)
+                .map_err(|e| ConfigError::Parse(e.to_string()))?;
+        // settings left unset are not serialized, so missing keys are
+        // added and left to the config's unknown field check
+        let mut node = &mut root;
+        for name in key.split('.') {
+            node = node.as_table_mut()
+                    .map(|table| table.entry(name)
+                            .or_insert_with(|| toml::Value::Table(Default::default())))
+                    .ok_or_else(|| ConfigError::Parse(
+                            format!("unknown setting '{}'", key)))?;
+        }
+        *node = value;
+        *self = root.try_into().map_err(|e: toml::de::Error| {
+            ConfigError::Parse(format!("{}: {}", key, e))
+        })?;
+        Ok(())
+    }
+
+    /// Lays the configured level, if any, over the playground and snake
+    /// settings.
+    pub fn apply_level(&mut self) -> Result<(), ConfigError> {
+        if let Some(name) = self.level.clone() {
+            let level = Level::find(&name).map_err(|e| {
+                ConfigError::Invalid(format!("level {}: {}", name, e))
+            })?;
+            level.apply(self);
+        }
+        Ok(())
+    }
+
+    /// Sets the number of players, dropping extra rivals or adding
+    /// default ones that start mirrored from the first snake.
+    pub fn set_player_count(&mut self, count: usize) {
+        self.rivals.truncate(count.saturating_sub(1));
+        while self.rivals.len() + 1 < count {
+            let rival = self.snake.mirrored(self.rivals.len(), &self.playground);
+            self.rivals.push(rival);
+        }
+    }
+
+    /// The settings of the snake of `player`, the first one being
+    /// `snake`.
+    pub fn get_snake_mut(&mut self, player: usize) -> Option<&mut SnakeConfig> {
+        match player {
+            0 => Some(&mut self.snake),
+            _ => self.rivals.get_mut(player - 1),
+        }
+    }
+
+    /// Checks the settings describe a playable game, e.g. that the snakes
+    /// fit on the board.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let playground = &self.playground;
+        let snake = &self.snake;
+        let border = playground.border_width;
+        // the border on both sides and two blocks inside it
+        let least = border.checked_mul(2).and_then(|width| width.checked_add(2))
+                .ok_or_else(|| invalid("playground.border_width is too large"))?;
+        if playground.width < least || playground.height < least {
+            return Err(invalid("the playground has no room inside its border"));
+        }
+        let board = playground.build();
+        let (width, height) = (playground.width, playground.height);
+        let in_bounds = |position: &Position| {
+            position.0 < width && position.1 < height
+        };
+        if !playground.walls.iter().chain(playground.food_zone.iter())
+                .all(in_bounds) {
+            return Err(invalid("walls and food zone must be on the playground"));
+        }
+        let closed = (0..width).all(|column| {
+            board.is_wall(&Position (column, 0))
+                    && board.is_wall(&Position (column, height - 1))
+        }) && (0..height).all(|row| {
+            board.is_wall(&Position (0, row))
+                    && board.is_wall(&Position (width - 1, row))
+        });
+        if !playground.wrap && !closed {
+            return Err(invalid("a walled playground must be closed on every edge"));
+        }
+        let snakes: Vec<&SnakeConfig> = Some(snake).into_iter()
+                .chain(self.rivals.iter())
+                .collect();
+        let mut colors = vec![playground.color, playground.border_color];
+        colors.extend(snakes.iter().map(|snake| snake.color));
+        if colors.iter().flatten().any(|c| !(0.0..=1.0).contains(c)) {
+            return Err(invalid("color components must be between 0 and 1"));
+        }
+        if snakes.iter().any(|snake| snake.init_length == 0) {
+            return Err(invalid("the snake needs at least one body block"));
+        }
+        for bot in snakes.iter().filter_map(|snake| snake.bot.as_ref()) {
+            if controller::by_name(bot).is_none() {
+                return Err(ConfigError::Invalid(format!("unknown bot '{}'", bot)));
+            }
+        }
+        let free = |column: i64, row: i64| {
+            column >= i64::from(border)
+                    && row >= i64::from(border)
+                    && column < i64::from(width - border)
+                    && row < i64::from(height - border)
+                    && !board.is_wall(&Position (column as u32, row as u32))
+        };
+        let mut snake_blocks = HashSet::new();
+        for snake in snakes.iter() {
+            let Position (column, row) = snake.init_position;
+            let (columns, rows) = snake.init_direction.opposite().offset();
+            for distance in 0..=i64::from(snake.init_length) {
+                let column = i64::from(column) + i64::from(columns) * distance;
+                let row = i64::from(row) + i64::from(rows) * distance;
+                if !free(column, row) {
+                    return Err(invalid("the snake does not fit on the playground"));
+                }
+                if !snake_blocks.insert((column, row)) {
+                    return Err(invalid("the snakes overlap"));
+                }
+            }
+        }
+        let length = snake_blocks.len() as i64;
+        let free_cells = if playground.food_zone.is_empty() {
+            let cells = (border..width - border).flat_map(|column| {
+                (border..height - border).map(move |row| (column, row))
+            });
+            cells.filter(|&(column, row)| {
+                free(i64::from(column), i64::from(row))
+            }).count() as i64 - length
+        } else {
+            playground.food_zone.iter()
+                    .filter(|position| !board.is_wall(position))
+                    .count() as i64
+        };
+        if free_cells < 2 {
+            return Err(invalid("there is no room left for food"));
+        }
+        if !is_positive(self.timing.move_delay) {
+            return Err(invalid("timing.move_delay must be positive"));
+        }
+        if !is_positive(self.bonus.disappear_after) {
+            return Err(invalid("bonus.disappear_after must be positive"));
+        }
+        Ok(())
+    }
+
+    pub fn build_game(&self) -> Game {
+        let game = Game::new(
+            self.playground.build(),
+            self.snake.build(),
+            self.food.build(),
+            self.bonus.build(),
+            self.timing.move_delay)
+            .with_rivals(self.build_rivals());
+        self.attach_bots(game)
+    }
+
+    pub fn build_game_with_seed(&self, seed: u64) -> Game {
+        let game = Game::with_seed(
+            self.playground.build(),
+            self.snake.build(),
+            self.food.build(),
+            self.bonus.build(),
+            self.timing.move_delay,
+            seed)
+            .with_rivals(self.build_rivals());
+        self.attach_bots(game)
+    }
+
+    fn build_rivals(&self) -> Vec<Snake> {
+        self.rivals.iter().map(SnakeConfig::build).collect()
+    }
+
+    fn attach_bots(&self, mut game: Game) -> Game {
+        let snakes = Some(&self.snake).into_iter().chain(self.rivals.iter());
+        for (player, snake) in snakes.enumerate() {
+            if let Some(bot) = &snake.bot {
+                game.set_controller(player, controller::by_name(bot));
+            }
+        }
+        game
+    }
+
+}
+
+
+impl PlaygroundConfig {
+
+    pub fn build(&self) -> Playground {
+        Playground::new(
+            self.width,
+            self.height,
+            self.color,
+            self.border_color,
+            self.border_width,
+            self.wrap)
+            .with_walls(self.walls.clone())
+            .with_food_zone(self.food_zone.clone())
+    }
+
+}
+
+
+impl SnakeConfig {
+
+    pub fn build(&self) -> Snake {
+        let head = Block::new(
+            self.init_position.clone(),
+            Shape::Circle(self.color));
+        Snake::new(
+            head,
+            Shape::Square(self.color),
+            self.init_direction.clone(),
+            self.init_length)
+    }
+
+    /// A snake for rival number `index` (0 for the second player),
+    /// starting from this one's position mirrored across the playground:
+    /// through its center, then left to right, then top to bottom.
+    pub fn mirrored(&self, index: usize, playground: &PlaygroundConfig)
+            -> SnakeConfig {
+        let Position (column, row) = self.init_position;
+        let mirrored_column = playground.width.saturating_sub(column + 1);
+        let mirrored_row = playground.height.saturating_sub(row + 1);
+        let horizontal = |direction: &Direction| match direction {
+            Direction::Left | Direction::Right => direction.opposite(),
+            Direction::Up | Direction::Down => direction.clone(),
+        };
+        let (init_position, init_direction) = match index % 3 {
+            0 => (Position (mirrored_column, mirrored_row),
+                    self.init_direction.opposite()),
+            1 => (Position (mirrored_column, row),
+                    horizontal(&self.init_direction)),
+            _ => (Position (column, mirrored_row),
+                    horizontal(&self.init_direction).opposite()),
+        };
+        SnakeConfig {
+            init_position,
+            init_direction,
+            init_length: self.init_length,
+            color: RIVAL_COLORS[index % RIVAL_COLORS.len()],
+            bot: None,
+        }
+    }
+
+}
+
+
+impl FoodConfig {
+
+    pub fn build(&self) -> Food {
+        Food::frog(self.calories)
+    }
+
+}
+
+
+impl BonusConfig {
+
+    pub fn build(&self) -> Food {
+        Food::mouse(self.calories, self.disappear_after)
+    }
+
+}
+
+
+impl fmt::Display for ConfigError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(error) => write!(f, "{}", error),
+            ConfigError::Parse(message) => write!(f, "{}", message),
+            ConfigError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+
+}
+
+
+impl std::error::Error for ConfigError {}
+
+
+impl OverrideError {
+
+    /// What a program ends with on this error: 2 for a command line
+    /// mistake, as for any other bad argument, and 1 otherwise.
+    pub fn get_exit_code(&self) -> i32 {
+        match self {
+            OverrideError::Setting(_) => 2,
+            _ => 1,
+        }
+    }
+
+}
+
+
+impl fmt::Display for OverrideError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OverrideError::Load(path, error) => write!(f,
+                    "Failed to load config {}: {}", path.display(), error),
+            OverrideError::Setting(message) => write!(f, "{}", message),
+            OverrideError::Level(error) => write!(f, "Failed to load {}", error),
+            OverrideError::Invalid(error) => write!(f,
+                    "Invalid configuration: {}", error),
+        }
+    }
+
+}
+
+
+impl std::error::Error for OverrideError {}
+
+
+impl From<io::Error> for ConfigError {
+
+    fn from(error: io::Error) -> ConfigError {
+        ConfigError::Io(error)
+    }
+
+}
+
+
+
+fn invalid(message: &str) -> ConfigError {
+    ConfigError::Invalid(String::from(message))
+}
+
+fn is_positive(value: f64) -> bool {
+    value > 0.0
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn refusal(config: &Config) -> String {
+        match config.validate() {
+            Err(ConfigError::Invalid(message)) => message,
+            Err(error) => panic!("refused for the wrong reason: {}", error),
+            Ok(()) => panic!("the config was accepted"),
+        }
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        assert!(Config::default().validate().is_ok());
+    }
+
+    #[test]
+    fn a_huge_border_is_refused_without_overflowing() {
+        let mut config = Config::default();
+        config.playground.border_width = u32::MAX;
+        assert_eq!(refusal(&config), "playground.border_width is too large");
+        config.playground.border_width = u32::MAX / 2 - 1;
+        assert_eq!(refusal(&config), "the playground has no room inside its border");
+    }
+
+    #[test]
+    fn validate_refuses_unplayable_settings() {
+        let mut config = Config::default();
+        config.snake.init_position = Position (40, 3);
+        assert_eq!(refusal(&config), "the snake does not fit on the playground");
+
+        let mut config = Config::default();
+        config.set_player_count(2);
+        config.rivals[0].init_position = config.snake.init_position.clone();
+        config.rivals[0].init_direction = config.snake.init_direction.clone();
+        assert_eq!(refusal(&config), "the snakes overlap");
+
+        let mut config = Config::default();
+        config.playground.border_width = 0;
+        assert_eq!(refusal(&config), "a walled playground must be closed on every edge");
+        config.playground.wrap = true;
+        assert!(config.validate().is_ok());
+
+        let mut config = Config::default();
+        config.timing.move_delay = 0.0;
+        assert_eq!(refusal(&config), "timing.move_delay must be positive");
+
+        let mut config = Config::default();
+        config.snake.bot = Some(String::from("psychic"));
+        assert_eq!(refusal(&config), "unknown bot 'psychic'");
+    }
+
+    #[test]
+    fn set_changes_a_single_setting() {
+        let mut config = Config::default();
+        config.set("playground.width=40").unwrap();
+        config.set("snake.init_direction=up").unwrap();
+        config.set("snake.bot=\"bfs\"").unwrap();
+        assert_eq!(config.playground.width, 40);
+        assert!(config.snake.init_direction == Direction::Up);
+        assert_eq!(config.snake.bot.as_deref(), Some("bfs"));
+        assert_eq!(config.playground.height, PLAYGROUND_HEIGHT);
+    }
+
+    #[test]
+    fn set_refuses_bad_assignments() {
+        let mut config = Config::default();
+        assert!(matches!(config.set("playground.width"), Err(ConfigError::Parse(_))));
+        assert!(matches!(config.set("playground.depth=3"), Err(ConfigError::Parse(_))));
+        assert!(matches!(config.set("playground.width=wide"), Err(ConfigError::Parse(_))));
+        assert_eq!(config.playground.width, PLAYGROUND_WIDTH);
+    }
+
+    #[test]
+    fn overrides_are_applied_and_checked() {
+        let mut config = Config::default();
+        let overrides = Overrides {
+            settings: vec![String::from("food.calories=3")],
+            width: Some(40),
+            speed: Some(5.0),
+            players: Some(1),
+            bots: vec![String::from("greedy")],
+            ..Overrides::default()
+        };
+        config.apply_overrides(&overrides).unwrap();
+        assert_eq!(config.food.calories, 3);
+        assert_eq!(config.playground.width, 40);
+        assert!((config.timing.move_delay - 0.2).abs() < 1e-9);
+        assert_eq!(config.rivals.len(), 1);
+        assert_eq!(config.rivals[0].bot.as_deref(), Some("greedy"));
+    }
+
+    #[test]
+    fn bad_overrides_are_refused() {
+        let too_many = Overrides {
+            players: Some(usize::from(MAX_PLAYERS) + 1),
+            ..Overrides::default()
+        };
+        let error = Config::default().apply_overrides(&too_many).unwrap_err();
+        assert!(matches!(error, OverrideError::Setting(_)));
+        assert_eq!(error.get_exit_code(), 2);
+
+        let campaign_level = Overrides {
+            campaign: true,
+            level: Some(String::from("box")),
+            ..Overrides::default()
+        };
+        assert!(matches!(Config::default().apply_overrides(&campaign_level),
+                Err(OverrideError::Setting(_))));
+
+        let too_narrow = Overrides { width: Some(3), ..Overrides::default() };
+        let error = Config::default().apply_overrides(&too_narrow).unwrap_err();
+        assert!(matches!(error, OverrideError::Invalid(_)));
+        assert_eq!(error.get_exit_code(), 1);
+    }
+
+}
@@ -0,0 +1,359 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::game::Game;
+use crate::grid::{Position, Direction};
+
+
+const STRATEGIES: [&str; 3] = ["greedy", "bfs", "hamilton"];
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+
+/// Steers a snake in place of a player. Before every tick the game asks
+/// the controller of each living snake where to go next, passing itself
+/// as a read-only view of the board, the snakes, the food and the bonus.
+///
+/// Controllers must be deterministic for replays of their games to play
+/// out the same.
+pub trait Controller: Send {
+
+    /// The direction the snake of `player` should take on the next step.
+    fn next_direction(&mut self, game: &Game, player: usize) -> Direction;
+
+    /// The name the strategy is picked by, see `by_name`.
+    fn name(&self) -> &str;
+
+}
+
+/// Heads straight for the closest food, only avoiding what is right in
+/// front of the snake.
+pub struct Greedy;
+
+/// Follows the shortest path to the food around walls and snakes. With no
+/// path, it moves to where the snake has the most room left.
+pub struct Bfs;
+
+/// Runs along a cycle through every block of an open board, so the snake
+/// never traps itself however long it grows. Boards without such a cycle,
+/// e.g. with walls inside, and food off the cycle fall back to `Bfs`.
+#[derive(Default)]
+pub struct Hamilton {
+    cycle: Option<HashMap<Position, Direction>>,
+    board: Option<(u32, u32, u32)>,
+}
+
+
+/// Builds the strategy called `name`: `greedy`, `bfs` or `hamilton`.
+pub fn by_name(name: &str) -> Option<Box<dyn Controller>> {
+    match name {
+        "greedy" => Some(Box::new(Greedy)),
+        "bfs" => Some(Box::new(Bfs)),
+        "hamilton" => Some(Box::new(Hamilton::default())),
+        _ => None,
+    }
+}
+
+pub fn strategy_names() -> impl Iterator<Item = &'static str> {
+    STRATEGIES.iter().cloned()
+}
+
+/// Blocks between two positions, the short way round on a wrapping board.
+pub fn distance(game: &Game, from: &Position, to: &Position) -> u32 {
+    let playground = game.get_playground();
+    let columns = (i64::from(from.0) - i64::from(to.0)).unsigned_abs() as u32;
+    let rows = (i64::from(from.1) - i64::from(to.1)).unsigned_abs() as u32;
+    if !playground.is_wrapping() {
+        return columns + rows;
+    }
+    let border = playground.get_border_width() * 2;
+    let width = playground.get_width() - border;
+    let height = playground.get_height() - border;
+    columns.min(width - columns) + rows.min(height - rows)
+}
+
+
+impl Controller for Greedy {
+
+    fn next_direction(&mut self, game: &Game, player: usize) -> Direction {
+        let snake = &game.get_snakes()[player];
+        let targets = targets(game);
+        safe_moves(game, player)
+                .min_by_key(|(_, position)| {
+                    targets.iter()
+                            .map(|target| distance(game, position, target))
+                            .min()
+                            .unwrap_or(0)
+                })
+                .map(|(direction, _)| direction)
+                .unwrap_or_else(|| snake.get_direction().clone())
+    }
+
+    fn name(&self) -> &str {
+        "greedy"
+    }
+
+}
+
+
+impl Controller for Bfs {
+
+    fn next_direction(&mut self, game: &Game, player: usize) -> Direction {
+        shortest_path_direction(game, player)
+                .or_else(|| roomiest_direction(game, player))
+                .unwrap_or_else(|| game.get_snakes()[player].get_direction().clone())
+    }
+
+    fn name(&self) -> &str {
+        "bfs"
+    }
+
+}
+
+
+impl Controller for Hamilton {
+
+    fn next_direction(&mut self, game: &Game, player: usize) -> Direction {
+        let playground = game.get_playground();
+        let board = (playground.get_width(), playground.get_height(),
+                playground.get_border_width());
+        if self.board != Some(board) {
+            self.board = Some(board);
+            self.cycle = build_cycle(game);
+        }
+        let snake = &game.get_snakes()[player];
+        let on_cycle = self.cycle.as_ref()
+                // food left out of the cycle is fetched the short way
+                .filter(|cycle| targets(game).iter()
+                        .all(|target| cycle.contains_key(target)))
+                .and_then(|cycle| cycle.get(snake.get_head_position()))
+                .filter(|direction| safe_moves(game, player)
+                        .any(|(safe, _)| safe == **direction));
+        match on_cycle {
+            Some(direction) => direction.clone(),
+            // off the cycle, e.g. on the first steps, until the snake
+            // can join it
+            None => Bfs.next_direction(game, player),
+        }
+    }
+
+    fn name(&self) -> &str {
+        "hamilton"
+    }
+
+}
+
+
+
+// the food, and the bonus while it shows
+fn targets(game: &Game) -> Vec<Position> {
+    let mut targets = vec![game.get_food().get_position().clone()];
+    if let Some(bonus) = game.get_bonus() {
+        targets.push(bonus.get_position().clone());
+    }
+    targets
+}
+
+// blocks no snake can move into on the next step
+fn blocked(game: &Game) -> HashSet<Position> {
+    let mut blocked = HashSet::new();
+    for (player, snake) in game.get_snakes().iter().enumerate() {
+        if !game.is_alive(player) {
+            continue;
+        }
+        blocked.insert(snake.get_head_position().clone());
+        blocked.extend(snake.get_body().map(|block| block.get_position().clone()));
+    }
+    blocked
+}
+
+fn is_free(game: &Game, blocked: &HashSet<Position>, position: &Position)
+        -> bool {
+    !blocked.contains(position) && !game.get_playground().is_wall(position)
+}
+
+// the directions the snake of `player` can take without dying right away,
+// with the block each one leads to
+fn safe_moves<'a>(game: &'a Game, player: usize)
+        -> impl Iterator<Item = (Direction, Position)> + 'a {
+    let snake = &game.get_snakes()[player];
+    let blocked = blocked(game);
+    let head = snake.get_head_position().clone();
+    let back = snake.get_direction().opposite();
+    DIRECTIONS.iter()
+            .filter(move |direction| **direction != back)
+            .map(move |direction| (direction.clone(),
+                    game.get_playground().next_position(&head, direction)))
+            .filter(move |(_, position)| is_free(game, &blocked, position))
+}
+
+// the first step of the shortest path to any target
+fn shortest_path_direction(game: &Game, player: usize) -> Option<Direction> {
+    let playground = game.get_playground();
+    let targets = targets(game);
+    let blocked = blocked(game);
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    for (direction, position) in safe_moves(game, player) {
+        if seen.insert(position.clone()) {
+            queue.push_back((position, direction));
+        }
+    }
+    while let Some((position, first_step)) = queue.pop_front() {
+        if targets.contains(&position) {
+            return Some(first_step);
+        }
+        for direction in DIRECTIONS.iter() {
+            let next = playground.next_position(&position, direction);
+            if is_free(game, &blocked, &next) && seen.insert(next.clone()) {
+                queue.push_back((next, first_step.clone()));
+            }
+        }
+    }
+    None
+}
+
+// the safe direction leading to the most free blocks
+fn roomiest_direction(game: &Game, player: usize) -> Option<Direction> {
+    let playground = game.get_playground();
+    let blocked = blocked(game);
+    safe_moves(game, player)
+            .max_by_key(|(_, start)| {
+                let mut seen: HashSet<Position> = Some(start.clone()).into_iter().collect();
+                let mut queue: VecDeque<Position> = Some(start.clone()).into_iter().collect();
+                while let Some(position) = queue.pop_front() {
+                    for direction in DIRECTIONS.iter() {
+                        let next = playground.next_position(&position, direction);
+                        if is_free(game, &blocked, &next) && seen.insert(next.clone()) {
+                            queue.push_back(next);
+                        }
+                    }
+                }
+                seen.len()
+            })
+            .map(|(direction, _)| direction)
+}
+
+// a cycle through every block inside the border, as the direction to
+// leave each block by: along the first row, then zigzag back through the
+// other columns and up the first column. It needs an even number of rows,
+// or of columns going the other way round. No cycle covers an odd by odd
+// board, so there the last row is taken in by detours down from the row
+// above, two blocks at a time, which leaves its last block out. Boards
+// with walls inside get no cycle.
+fn build_cycle(game: &Game) -> Option<HashMap<Position, Direction>> {
+    let playground = game.get_playground();
+    let border = playground.get_border_width();
+    let width = playground.get_width() - border * 2;
+    let height = playground.get_height() - border * 2;
+    let odd = width % 2 == 1 && height % 2 == 1;
+    let rows = if odd { height - 1 } else { height };
+    if width < 2 || rows < 2 || playground.get_walls().next().is_some() {
+        return None;
+    }
+    let transposed = rows % 2 == 1;
+    let (columns, rows) = if transposed { (rows, width) } else { (width, rows) };
+    let mut cycle = HashMap::new();
+    for row in 0..rows {
+        for column in 0..columns {
+            let direction = if column == 0 {
+                if row == 0 { Direction::Right } else { Direction::Up }
+            } else if row == 0 {
+                if column == columns - 1 { Direction::Down } else { Direction::Right }
+            } else if row % 2 == 1 {
+                if column == 1 {
+                    if row == rows - 1 { Direction::Left } else { Direction::Down }
+                } else {
+                    Direction::Left
+                }
+            } else if column == columns - 1 {
+                Direction::Down
+            } else {
+                Direction::Right
+            };
+            let (column, row, direction) = if transposed {
+                (row, column, transpose(&direction))
+            } else {
+                (column, row, direction)
+            };
+            cycle.insert(Position::new(column + border, row + border), direction);
+        }
+    }
+    if odd {
+        // the row above runs left, so each detour goes down, left and back up
+        let (above, last) = (height - 2 + border, height - 1 + border);
+        for column in (border..width - 2 + border).step_by(2) {
+            cycle.insert(Position::new(column + 1, above), Direction::Down);
+            cycle.insert(Position::new(column + 1, last), Direction::Left);
+            cycle.insert(Position::new(column, last), Direction::Up);
+        }
+    }
+    Some(cycle)
+}
+
+fn transpose(direction: &Direction) -> Direction {
+    match direction {
+        Direction::Up => Direction::Left,
+        Direction::Left => Direction::Up,
+        Direction::Down => Direction::Right,
+        Direction::Right => Direction::Down,
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::config::Config;
+
+    fn cycle_of(width: u32, height: u32) -> HashMap<Position, Direction> {
+        let mut config = Config::default();
+        config.playground.width = width;
+        config.playground.height = height;
+        build_cycle(&config.build_game_with_seed(0)).unwrap()
+    }
+
+    // follows the cycle from one of its blocks back to it, checking every
+    // step goes to a neighbour
+    fn assert_closed(cycle: &HashMap<Position, Direction>) {
+        let start = cycle.keys().next().unwrap().clone();
+        let mut seen = HashSet::new();
+        let mut position = start.clone();
+        loop {
+            assert!(seen.insert(position.clone()));
+            let (columns, rows) = cycle[&position].offset();
+            let Position (column, row) = position;
+            position = Position::new(column.checked_add_signed(columns).unwrap(),
+                    row.checked_add_signed(rows).unwrap());
+            assert!(cycle.contains_key(&position));
+            if position == start {
+                break;
+            }
+        }
+        assert_eq!(seen.len(), cycle.len());
+    }
+
+    #[test]
+    fn cycle_covers_even_boards_whole() {
+        for (width, height) in [(20, 20), (11, 10), (12, 9)].iter() {
+            let cycle = cycle_of(*width, *height);
+            assert_eq!(cycle.len() as u32, (width - 2) * (height - 2));
+            assert_closed(&cycle);
+        }
+    }
+
+    #[test]
+    fn cycle_leaves_one_corner_of_odd_boards_out() {
+        for (width, height) in [(35, 25), (11, 11), (13, 9)].iter() {
+            let cycle = cycle_of(*width, *height);
+            assert_eq!(cycle.len() as u32, (width - 2) * (height - 2) - 1);
+            assert!(!cycle.contains_key(&Position::new(width - 2, height - 2)));
+            assert_closed(&cycle);
+        }
+    }
+
+}
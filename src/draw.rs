@@ -1,95 +1,247 @@
-use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::fmt;
 use piston_window::*;
-use piston_window::types::Color;
-use find_folder::Search;
+use piston_window::character::CharacterCache;
+use crate::grid::{Block, Shape, Position, Direction, Color};
+use crate::snake::Snake;
+use crate::food::{Food, FOOD_SPRITE, BONUS_SPRITE};
+use crate::playground::Playground;
+use crate::game::{Game, Status};
+use crate::highscores::HighScores;
+use crate::campaign::Campaign;
+use crate::menu::Menu;
+use crate::net::Seat;
+use crate::theme::{Theme, ThemeError};
 
 
+// block size the window opens at, and fonts are sized for
 const BLOCK_SIZE: f64 = 12.0;
+const HUD_ROWS: u32 = 2;
 const WHITE_COLOR: Color = [1.0, 1.0, 1.0, 1.0];
+const GAMEOVER_COLOR: Color = [0.0, 0.0, 0.0, 0.90];
+const PAUSED_COLOR: Color = [0.0, 0.0, 0.0, 0.50];
+const SCORE_FONT_SIZE: u32 = 12;
+const OVERLAY_FONT_SIZE: u32 = 20;
+const MISSING_COLOR: Color = [1.0, 0.0, 1.0, 1.0];
+const HEAD_SPRITE: &str = "head";
+const BODY_SPRITE: &str = "body";
+const TAIL_SPRITE: &str = "tail";
+const CORNER_SPRITE: &str = "corner";
+// width of the snake's body, in blocks
+const BODY_WIDTH: f64 = 0.8;
+// rows between a banner and the text under it
+const BANNER_GAP: u32 = 3;
 
 
-pub struct Block {
-    position: Position,
-    shape: Shape,
+#[derive(PartialEq)]
+pub struct Coord (pub f64, pub f64);
+
+/// Where the grid sits in the window. Blocks are as large as the window
+/// allows, and the grid is centred with bars along the sides it leaves
+/// empty, so it keeps its shape whatever the size of the window.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Viewport {
+    columns: u32,
+    rows: u32,
+    block_size: f64,
+    left: f64,
+    top: f64,
 }
 
-#[allow(dead_code)]
-#[derive(Clone)]
-pub enum Shape {
-    Square(Color),
-    Circle(Color),
-    Triangle(Color),
-    Image(String),
+/// The sprites and the font of a theme, loaded once when the window opens
+/// or the theme changes, and looked up by role on every frame: `food`,
+/// `bonus`, `head`, `body` and `tail`.
+pub struct Assets {
+    theme: Theme,
+    textures: HashMap<String, G2dTexture>,
+    glyphs: Glyphs,
 }
 
+// how a piece of snake is drawn: a sprite tinted with the snake's color,
+// or a plain shape
+enum Look<'a> {
+    Sprite(&'a G2dTexture, Color),
+    Shape(Shape),
+}
 
-#[derive(Clone, PartialEq)]
-pub struct Position (pub u32, pub u32);
+#[derive(Debug)]
+pub enum AssetError {
+    /// A file of the theme could not be read
+    Theme(ThemeError),
+    /// A file was read but is not a valid image or font
+    Load(String, String),
+}
 
-#[derive(PartialEq)]
-pub struct Coord (pub f64, pub f64);
 
-#[derive(Clone, PartialEq)]
-pub enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
+impl Position {
+
+    pub fn to_coord(&self, viewport: &Viewport) -> Coord {
+        let x = viewport.left + f64::from(self.0) * viewport.block_size;
+        let y = viewport.top + f64::from(self.1) * viewport.block_size;
+        Coord (x, y)
+    }
+
+}
+
+
+impl Coord {
+
+    pub fn as_array(&self) -> [f64; 2] {
+        let Coord (x, y) = *self;
+        [x, y]
+    }
+
 }
 
 
-impl Direction {
+impl Viewport {
 
-    pub fn opposite(&self) -> Direction {
-        match self {
-            Direction::Up => Direction::Down,
-            Direction::Down => Direction::Up,
-            Direction::Left => Direction::Right,
-            Direction::Right => Direction::Left,
+    /// Fits a grid of `columns` by `rows` blocks in a window of `size`.
+    pub fn fit(columns: u32, rows: u32, size: [f64; 2]) -> Viewport {
+        let [width, height] = size;
+        let block_size = (width / f64::from(columns))
+                .min(height / f64::from(rows))
+                .max(0.0);
+        Viewport {
+            columns,
+            rows,
+            block_size,
+            left: (width - block_size * f64::from(columns)) / 2.0,
+            top: (height - block_size * f64::from(rows)) / 2.0,
         }
     }
 
+    /// Fits `playground` with the score line under it in a window of
+    /// `size`.
+    pub fn of(playground: &Playground, size: [f64; 2]) -> Viewport {
+        Viewport::fit(playground.get_width(),
+                playground.get_height() + HUD_ROWS, size)
+    }
+
+    pub fn get_block_size(&self) -> f64 {
+        self.block_size
+    }
+
+    /// How much larger than the default blocks are, to size text along.
+    pub fn get_scale(&self) -> f64 {
+        self.block_size / BLOCK_SIZE
+    }
+
+    /// Width and height of `columns` by `rows` blocks, in pixels.
+    pub fn measure(&self, columns: u32, rows: u32) -> [f64; 2] {
+        [f64::from(columns) * self.block_size, f64::from(rows) * self.block_size]
+    }
+
+    // the row of a banner, halfway down the board
+    fn get_banner_row(&self) -> u32 {
+        self.rows.saturating_sub(HUD_ROWS) / 2
+    }
+
+    // the column lists of text start at
+    fn get_text_column(&self) -> u32 {
+        self.columns / 6
+    }
+
+    // scales a font size along with the blocks
+    fn scale_font(&self, size: u32) -> u32 {
+        (f64::from(size) * self.get_scale()).round().max(1.0) as u32
+    }
+
 }
 
 
-impl Position {
+impl Assets {
+
+    /// Loads the font and the sprites of `theme`.
+    pub fn load(factory: &mut GfxFactory, theme: Theme)
+            -> Result<Assets, AssetError> {
+        let glyphs = load_font(factory, &theme)?;
+        let sprites = [
+            (FOOD_SPRITE, Some(&theme.sprites.food)),
+            (BONUS_SPRITE, Some(&theme.sprites.bonus)),
+            (HEAD_SPRITE, theme.sprites.head.as_ref()),
+            (BODY_SPRITE, theme.sprites.body.as_ref()),
+            (TAIL_SPRITE, theme.sprites.tail.as_ref()),
+            (CORNER_SPRITE, theme.sprites.corner.as_ref()),
+        ];
+        let mut textures = HashMap::new();
+        for (role, file) in sprites.iter() {
+            if let Some(file) = file {
+                textures.insert(String::from(*role),
+                        load_texture(factory, &theme, file)?);
+            }
+        }
+        Ok(Assets { theme, textures, glyphs })
+    }
 
-    pub fn new(columns: u32, rows: u32) -> Position {
-        Position (columns, rows)
+    /// Replaces the assets with those of `theme`. Nothing changes when
+    /// they fail to load.
+    pub fn switch(&mut self, factory: &mut GfxFactory, theme: Theme)
+            -> Result<(), AssetError> {
+        *self = Assets::load(factory, theme)?;
+        Ok(())
     }
 
-    pub fn to_coord(&self) -> Coord {
-        let x = f64::from(self.0) * BLOCK_SIZE;
-        let y = f64::from(self.1) * BLOCK_SIZE;
-        Coord (x, y)
+    pub fn get_theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    pub fn get_texture(&self, name: &str) -> Option<&G2dTexture> {
+        self.textures.get(name)
+    }
+
+    pub fn get_text_color(&self) -> Color {
+        self.theme.get_text_color()
     }
 
-    pub fn shifted_by(&self, columns: i32, rows: i32,) -> Position {
-        Position::new(
-            (self.0 as i32 + columns) as u32, 
-            (self.1 as i32 + rows) as u32)
+    pub fn get_playground_color(&self, playground: &Playground) -> Color {
+        self.theme.get_playground_color(playground)
+    }
+
+    pub fn get_border_color(&self, playground: &Playground) -> Color {
+        self.theme.get_border_color(playground)
+    }
+
+    pub fn get_snake_color(&self, player: usize, snake: &Snake) -> Color {
+        self.theme.get_snake_color(player, snake)
     }
 
 }
 
 
-impl Coord {
+impl fmt::Display for AssetError {
 
-    #[allow(dead_code)]
-    pub fn new(x:f64, y:f64) -> Coord {
-        Coord (x, y)
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssetError::Theme(error) => write!(f, "{}", error),
+            AssetError::Load(file, reason) => write!(f, "{}: {}",
+                    file, reason),
+        }
     }
 
-    #[allow(dead_code)]
-    pub fn to_position(&self) -> Position {
-        let column = (self.0 / BLOCK_SIZE) as u32;
-        let row = (self.1 / BLOCK_SIZE) as u32;
-        Position (column, row)
+}
+
+
+impl std::error::Error for AssetError {}
+
+
+impl From<ThemeError> for AssetError {
+
+    fn from(error: ThemeError) -> AssetError {
+        AssetError::Theme(error)
     }
 
-    pub fn as_array(&self) -> [f64; 2] {
-        let Coord (x, y) = *self;
-        [x, y]
+}
+
+
+impl<'a> Look<'a> {
+
+    fn of(sprite: Option<&'a G2dTexture>, shape: &Shape, color: Color)
+            -> Look<'a> {
+        match sprite {
+            Some(texture) => Look::Sprite(texture, color),
+            None => Look::Shape(recolored(shape, color)),
+        }
     }
 
 }
@@ -97,61 +249,373 @@ impl Coord {
 
 impl Block {
 
-    pub fn new(position: Position, shape: Shape) -> Block {
-        Block {
-            position,
-            shape,
+    pub fn draw(&self, assets: &Assets, viewport: &Viewport,
+                    context: &Context, graphics: &mut G2d) {
+        draw_shape(self.get_shape(), &self.get_position().to_coord(viewport),
+                assets, viewport, context, graphics);
+    }
+
+}
+
+
+impl Playground {
+
+    /// The size of a window showing the board and its score line at the
+    /// default block size.
+    pub fn get_size(&self) -> [f64; 2] {
+        let rows = self.get_height() + HUD_ROWS;
+        [f64::from(self.get_width()) * BLOCK_SIZE, f64::from(rows) * BLOCK_SIZE]
+    }
+
+    pub fn draw(&self, assets: &Assets,
+            viewport: &Viewport, context: &Context, graphics: &mut G2d) {
+        let border_width = self.get_border_width();
+        let color = assets.get_playground_color(self);
+        let border_color = assets.get_border_color(self);
+        if self.is_wrapping() {
+            self.draw_passable_border(color, border_color, viewport,
+                    context, graphics);
+        } else {
+            draw_rectangle(
+                &Position (0, 0), 
+                self.get_width(), 
+                self.get_height(), 
+                border_color, 
+                viewport,
+                context, 
+                graphics
+            );
+        }
+        draw_rectangle(
+            &Position (border_width, border_width), 
+            self.get_width() - (border_width * 2), 
+            self.get_height() - (border_width * 2), 
+            color, 
+            viewport,
+            context, 
+            graphics
+        );
+        for wall in self.get_walls() {
+            draw_rectangle(
+                wall,
+                1,
+                1,
+                border_color,
+                viewport,
+                context,
+                graphics);
         }
     }
 
-    pub fn draw(&self, factory: &mut GfxFactory, 
-                    context: &Context, graphics: &mut G2d) {
-        let Coord (x, y) = self.position.to_coord();
-        match &self.shape {
-            Shape::Square(color) => rectangle(
-                *color, 
-                [x, y, BLOCK_SIZE, BLOCK_SIZE], 
-                context.transform,
-                graphics),
-            Shape::Circle(color) => ellipse(
-                *color, 
-                [x, y, BLOCK_SIZE, BLOCK_SIZE],
-                context.transform,
-                graphics),
-            Shape::Triangle(color) => polygon(
-                *color, 
-                &[
-                    self.position.shifted_by(1, 0).to_coord().as_array(), 
-                    self.position.shifted_by(0, 1).to_coord().as_array(), 
-                    self.position.shifted_by(1, 1).to_coord().as_array()
-                ], 
-                context.transform,
-                graphics),
-            Shape::Image(path) => {
-                let image   = Image::new().rect([x, y, BLOCK_SIZE, BLOCK_SIZE]);
-                let texture = Texture::from_path(factory, find_resource(path),
-                        Flip::None, &TextureSettings::new()).unwrap();
-                image.draw(&texture, &DrawState::default(), context.transform, graphics);
-            },
+    // a dashed border shows the snake can pass through the edges
+    fn draw_passable_border(&self, color: Color, border_color: Color,
+            viewport: &Viewport, context: &Context, graphics: &mut G2d) {
+        draw_rectangle(
+            &Position (0, 0),
+            self.get_width(),
+            self.get_height(),
+            color,
+            viewport,
+            context,
+            graphics);
+        for column in 0..self.get_width() {
+            for row in 0..self.get_height() {
+                let position = Position::new(column, row);
+                if self.in_border(&position) && (column + row) % 2 == 0 {
+                    draw_rectangle(
+                        &Position (column, row),
+                        1,
+                        1,
+                        border_color,
+                        viewport,
+                        context,
+                        graphics);
+                }
+            }
         }
     }
 
-    pub fn get_position(&self) -> &Position {
-        &self.position
+}
+
+
+impl Snake {
+
+    /// Draws the snake of `player` as one body, in the theme's color when
+    /// it has one: from the theme's body, corner and tail tiles, or else
+    /// as a band that bends around corners and tapers to the tail. Below
+    /// a `progress` of 1, the head and the tail are drawn that far along
+    /// their last step, so the snake glides from block to block.
+    pub fn draw(&self, player: usize, progress: f64, assets: &Assets,
+                viewport: &Viewport, context: &Context, graphics: &mut G2d) {
+        let color = assets.get_snake_color(player, self);
+        if assets.get_texture(BODY_SPRITE).is_some() {
+            self.draw_tiles(color, assets, viewport, context, graphics);
+        } else {
+            self.draw_band(color, progress, viewport, context, graphics);
+        }
+        let head_position = self.get_head_position();
+        let behind_head = self.get_body().next()
+                .map_or(head_position, Block::get_position);
+        let coord = glide(behind_head, head_position, progress, viewport);
+        let sprite = assets.get_texture(HEAD_SPRITE);
+        draw_piece(&Look::of(sprite, self.get_head().get_shape(), color),
+                &coord, self.get_direction(), assets, viewport, context,
+                graphics);
+        if sprite.is_none() {
+            draw_eyes(&coord, self.get_direction(), viewport, context, graphics);
+        }
     }
 
-    pub fn set_position(&mut self, position: Position) {
-        self.position = position;
+    // a tile per block, picked and turned by the blocks next to it; the
+    // tail moves a block at a time, as a tile cannot be cut short
+    fn draw_tiles(&self, color: Color, assets: &Assets, viewport: &Viewport,
+            context: &Context, graphics: &mut G2d) {
+        let body = assets.get_texture(BODY_SPRITE);
+        let corner = assets.get_texture(CORNER_SPRITE);
+        let tail = assets.get_texture(TAIL_SPRITE);
+        for (position, ahead, behind) in self.get_links() {
+            let coord = position.to_coord(viewport);
+            let (sprite, direction) = match behind {
+                None => match tail {
+                    Some(tail) => (Some(tail), ahead),
+                    None => {
+                        draw_tail(position, &ahead.opposite(), 0.0, color,
+                                viewport, context, graphics);
+                        continue;
+                    },
+                },
+                Some(behind) if behind == ahead.opposite() => (body, ahead),
+                // corner tiles join the bottom edge to the right one
+                Some(behind) => match corner {
+                    Some(corner) => {
+                        let edge = if behind.turned_left() == ahead {
+                            behind
+                        } else {
+                            ahead
+                        };
+                        (Some(corner), edge.opposite())
+                    },
+                    None => (body, ahead),
+                },
+            };
+            if let Some(sprite) = sprite {
+                draw_sprite(sprite, &coord, &direction, color, viewport,
+                        context, graphics);
+            }
+        }
     }
 
-    #[allow(dead_code)]
-    pub fn get_shape(&self) -> &Shape {
-        &self.shape
+    // a band narrower than the blocks, from the middle of each block to
+    // the edges it shares with the blocks next to it, rounded at corners
+    fn draw_band(&self, color: Color, progress: f64, viewport: &Viewport,
+            context: &Context, graphics: &mut G2d) {
+        let links = self.get_links();
+        for (position, ahead, behind) in links.iter() {
+            draw_arm(position, ahead, 0.5, color, viewport, context, graphics);
+            if let Some(behind) = behind {
+                draw_arm(position, behind, 0.5, color, viewport, context, graphics);
+                if *behind != ahead.opposite() {
+                    draw_joint(position, color, viewport, context, graphics);
+                }
+            }
+        }
+        // the tail trails off the last block, over the block it is
+        // leaving until the step is over
+        if let Some((position, ahead, _)) = links.last() {
+            let prev_tail = self.get_prev_tail();
+            let (back, trail) = if progress < 1.0 && prev_tail != *position {
+                (facing(position, prev_tail), 1.0 - progress.max(0.0))
+            } else {
+                (ahead.opposite(), 0.0)
+            };
+            if back != ahead.opposite() {
+                draw_joint(position, color, viewport, context, graphics);
+            }
+            draw_tail(position, &back, trail, color, viewport, context, graphics);
+        }
     }
 
-    #[allow(dead_code)]
-    pub fn set_shape(&mut self, shape: Shape) {
-        self.shape = shape;
+    // every body block, with the ways to the block ahead of it and to the
+    // one behind it, none for the last
+    fn get_links(&self) -> Vec<(&Position, Direction, Option<Direction>)> {
+        let positions: Vec<&Position> = Some(self.get_head_position())
+                .into_iter()
+                .chain(self.get_body().map(Block::get_position))
+                .collect();
+        positions.windows(2)
+                .enumerate()
+                .map(|(index, pair)| {
+                    let behind = positions.get(index + 2)
+                            .map(|behind| facing(pair[1], behind));
+                    (pair[1], facing(pair[1], pair[0]), behind)
+                })
+                .collect()
+    }
+
+}
+
+
+impl Food {
+
+    pub fn draw(&self, assets: &Assets,
+            viewport: &Viewport, context: &Context, graphics: &mut G2d) {
+        self.get_block().draw(assets, viewport, context, graphics);
+    }
+
+}
+
+
+impl Game {
+
+    /// Draws the game, with the snakes gliding between blocks when
+    /// `smooth`.
+    pub fn draw(&self, smooth: bool, assets: &mut Assets, 
+            viewport: &Viewport, context: &Context, graphics: &mut G2d) {
+        let playground = self.get_playground();
+        let progress = if smooth { self.get_step_progress() } else { 1.0 };
+        playground.draw(assets, viewport, context, graphics);
+        self.get_food().draw(assets, viewport, context, graphics);
+        if let Some(bonus) = self.get_bonus() {
+            bonus.draw(assets, viewport, context, graphics);
+        }
+        for (player, snake) in self.get_snakes().iter().enumerate() {
+            if self.is_alive(player) {
+                snake.draw(player, progress, assets, viewport, context,
+                        graphics);
+            }
+        }
+        draw_rectangle(
+            &Position (0, playground.get_height()),
+            playground.get_width(),
+            HUD_ROWS,
+            assets.get_border_color(playground),
+            viewport,
+            context,
+            graphics);
+        if self.get_player_count() > 1 {
+            self.draw_player_scores(assets, viewport, context, graphics);
+        } else {
+            draw_text(
+                &format!("Score: {}", self.get_score()), 
+                &Position (2, playground.get_height()+1), 
+                SCORE_FONT_SIZE, 
+                assets, 
+                viewport,
+                context, 
+                graphics);
+        }
+        if let (Some(target), 1) = (self.get_target(), self.get_player_count()) {
+            draw_text(
+                &format!("Goal: {} {}/{}", target.get_label(),
+                        target.progress(self), target.get_goal()),
+                &Position (14, playground.get_height()+1),
+                SCORE_FONT_SIZE,
+                assets,
+                viewport,
+                context,
+                graphics);
+        }
+
+        let row = viewport.get_banner_row();
+        match self.get_status() {
+            Status::Paused => draw_overlay("Paused", row, PAUSED_COLOR,
+                    assets, viewport, context, graphics),
+            Status::GameOver => {
+                let text = match (self.get_winner(), self.get_player_count()) {
+                    (_, 1) => String::from("Game Over"),
+                    (Some(player), _) => format!("Player {} Wins", player + 1),
+                    (None, _) => String::from("Draw"),
+                };
+                draw_overlay(&text, row, GAMEOVER_COLOR,
+                        assets, viewport, context, graphics)
+            },
+            Status::Cleared => draw_overlay("Stage Cleared", row, GAMEOVER_COLOR,
+                    assets, viewport, context, graphics),
+            Status::Running => (),
+        }
+    }
+
+    // each player's color and score along the bottom line
+    fn draw_player_scores(&self, assets: &mut Assets,
+            viewport: &Viewport, context: &Context, graphics: &mut G2d) {
+        let row = self.get_playground().get_height();
+        let players = self.get_snakes().iter().zip(self.get_scores());
+        for (player, (snake, score)) in players.enumerate() {
+            let column = 1 + 8 * player as u32;
+            draw_rectangle(
+                &Position (column, row),
+                1,
+                1,
+                assets.get_snake_color(player, snake),
+                viewport,
+                context,
+                graphics);
+            draw_text(
+                &format!("P{}: {}", player + 1, score),
+                &Position (column + 1, row + 1),
+                SCORE_FONT_SIZE,
+                assets,
+                viewport,
+                context,
+                graphics);
+        }
+    }
+
+    /// The size of a window showing the game at the default block size.
+    pub fn get_size(&self) -> [f64; 2] {
+        self.get_playground().get_size()
+    }
+
+}
+
+
+impl Menu {
+
+    /// Draws the menu over the empty `board`, the selected entry marked.
+    pub fn draw(&self, board: &Playground, assets: &mut Assets,
+            viewport: &Viewport, context: &Context, graphics: &mut G2d) {
+        board.draw(assets, viewport, context, graphics);
+        let top = viewport.rows / 4;
+        let column = viewport.get_text_column();
+        draw_overlay(self.get_title(), top, GAMEOVER_COLOR,
+                assets, viewport, context, graphics);
+        let mut row = top + BANNER_GAP;
+        for (index, entry) in self.get_entries().iter().enumerate() {
+            row += 1;
+            if row + HUD_ROWS >= viewport.rows {
+                break;
+            }
+            let marker = match self.get_selected() {
+                Some(selected) if selected == index => "> ",
+                Some(_) => "  ",
+                None => "",
+            };
+            draw_text(
+                &format!("{}{}", marker, entry),
+                &Position (column, row),
+                SCORE_FONT_SIZE,
+                assets,
+                viewport,
+                context,
+                graphics);
+        }
+        row += 2;
+        let lines = self.get_message().into_iter()
+                .chain(Some(self.get_hint()));
+        for line in lines {
+            if row + HUD_ROWS >= viewport.rows {
+                break;
+            }
+            draw_text(
+                line,
+                &Position (column, row),
+                SCORE_FONT_SIZE,
+                assets,
+                viewport,
+                context,
+                graphics);
+            row += 1;
+        }
     }
 
 }
@@ -159,35 +623,195 @@ impl Block {
 
 
 pub fn draw_rectangle(position: &Position, width: u32, height: u32, 
-            color: Color, context: &Context, graphics: &mut G2d) {
-    let Coord (x, y) = position.to_coord();
+            color: Color, viewport: &Viewport,
+            context: &Context, graphics: &mut G2d) {
+    let Coord (x, y) = position.to_coord(viewport);
+    let [width, height] = viewport.measure(width, height);
     rectangle(
         color, 
-        [x, y, (f64::from(width) * BLOCK_SIZE), 
-            (f64::from(height) * BLOCK_SIZE)], 
+        [x, y, width, height], 
         context.transform,
         graphics
     );
 }
 
-pub fn draw_text(text: &str, position: &Position, color: Color, size: u32,
-            factory: GfxFactory, context: &Context, graphics: &mut G2d) {
-    let Coord (x, y) = position.to_coord();
-    let font = find_resource("ExoExtraBold.ttf");
-    let mut glyphs = Glyphs::new(font, factory, TextureSettings::new()).unwrap();
-    piston_window::text(
-        color,
+/// Writes `text` in the theme's text color, `size` growing with the
+/// blocks.
+pub fn draw_text(text: &str, position: &Position, size: u32, assets: &mut Assets,
+            viewport: &Viewport, context: &Context, graphics: &mut G2d) {
+    let coord = position.to_coord(viewport);
+    write_text(text, &coord, viewport.scale_font(size), assets, context, graphics);
+}
+
+/// Lists the high score table under the Game Over banner, with a name
+/// prompt on top while a new record is being entered.
+pub fn draw_high_scores(scores: &HighScores, name_entry: Option<&str>,
+            playground: &Playground, assets: &mut Assets,
+            viewport: &Viewport, context: &Context, graphics: &mut G2d) {
+    let mut row = viewport.get_banner_row() + BANNER_GAP;
+    let column = viewport.get_text_column();
+    if let Some(name) = name_entry {
+        draw_text(
+            &format!("New record! Name: {}_", name),
+            &Position (column, row),
+            SCORE_FONT_SIZE,
+            assets,
+            viewport,
+            context,
+            graphics);
+        row += 2;
+    }
+    draw_centered_text(
+        "High Scores",
+        row,
+        SCORE_FONT_SIZE,
+        assets,
+        viewport,
+        context,
+        graphics);
+    for (rank, entry) in scores.get_entries().iter().enumerate() {
+        row += 1;
+        if row >= playground.get_height() {
+            break;
+        }
+        draw_text(
+            &format!("{}. {:<12} {:>5}  {}", rank + 1, entry.name,
+                    entry.score, entry.date),
+            &Position (column, row),
+            SCORE_FONT_SIZE,
+            assets,
+            viewport,
+            context,
+            graphics);
+    }
+}
+
+/// The transition screen under the Stage Cleared banner, announcing the
+/// stage `campaign` moved on to, or the end of the campaign once it is
+/// complete.
+pub fn draw_stage_cleared(campaign: &Campaign, playground: &Playground,
+            assets: &mut Assets, viewport: &Viewport, context: &Context,
+            graphics: &mut G2d) {
+    let stage = campaign.get_stage();
+    let lines = if !campaign.is_complete() {
+        [
+            format!("Next: stage {} of {}, {}", campaign.get_stage_number(),
+                    campaign.get_stage_count(), stage.get_name()),
+            format!("Goal: {}", stage.get_target()),
+            String::from("Press Enter to go on"),
+        ]
+    } else {
+        [
+            String::from("You beat the whole campaign!"),
+            String::new(),
+            String::from("Press Enter to start over"),
+        ]
+    };
+    let top = viewport.get_banner_row() + BANNER_GAP;
+    for (row, line) in (top..).zip(lines.iter()) {
+        if row >= playground.get_height() {
+            break;
+        }
+        draw_text(
+            line,
+            &Position (viewport.get_text_column(), row),
+            SCORE_FONT_SIZE,
+            assets,
+            viewport,
+            context,
+            graphics);
+    }
+}
+
+/// The players of a networked game under a `hint` line, below the Game
+/// Over banner or, before the first game, over the empty `board`.
+pub fn draw_lobby(seats: &[Seat], hint: &str, board: Option<&Playground>,
+            assets: &mut Assets, viewport: &Viewport,
+            context: &Context, graphics: &mut G2d) {
+    if let Some(board) = board {
+        board.draw(assets, viewport, context, graphics);
+        draw_overlay("Lobby", viewport.get_banner_row(), PAUSED_COLOR,
+                assets, viewport, context, graphics);
+    }
+    let mut row = viewport.get_banner_row() + BANNER_GAP;
+    let column = viewport.get_text_column();
+    draw_text(
+        hint,
+        &Position (column, row),
+        SCORE_FONT_SIZE,
+        assets,
+        viewport,
+        context,
+        graphics);
+    row += 1;
+    // the score line stays clear
+    for (index, seat) in seats.iter().enumerate() {
+        row += 1;
+        if row + HUD_ROWS >= viewport.rows {
+            break;
+        }
+        draw_text(
+            &format!("{}. {:<12} {}", index + 1, seat.name, seat.state),
+            &Position (column, row),
+            SCORE_FONT_SIZE,
+            assets,
+            viewport,
+            context,
+            graphics);
+    }
+}
+
+// darkens the whole grid with `text` across it on `row`
+fn draw_overlay(text: &str, row: u32, color: Color,
+            assets: &mut Assets, viewport: &Viewport,
+            context: &Context, graphics: &mut G2d) {
+    draw_rectangle(
+        &Position (0, 0), 
+        viewport.columns, 
+        viewport.rows, 
+        color, 
+        viewport,
+        context, 
+        graphics);
+    draw_centered_text(
+        text, 
+        row, 
+        OVERLAY_FONT_SIZE, 
+        assets, 
+        viewport,
+        context, 
+        graphics);
+}
+
+// writes `text` on `row`, halfway across the grid
+fn draw_centered_text(text: &str, row: u32, size: u32, assets: &mut Assets,
+            viewport: &Viewport, context: &Context, graphics: &mut G2d) {
+    let size = viewport.scale_font(size);
+    let width = assets.glyphs.width(size, text).unwrap_or(0.0);
+    let [grid_width, _] = viewport.measure(viewport.columns, 0);
+    let Coord (_, y) = Position (0, row).to_coord(viewport);
+    let x = viewport.left + ((grid_width - width) / 2.0).max(0.0);
+    write_text(text, &Coord (x, y), size, assets, context, graphics);
+}
+
+fn write_text(text: &str, coord: &Coord, size: u32, assets: &mut Assets,
+            context: &Context, graphics: &mut G2d) {
+    let Coord (x, y) = *coord;
+    // the font has a glyph for every character the game writes, so
+    // there is nothing to report
+    let _ = piston_window::text(
+        assets.get_text_color(),
         size,
         text,
-        &mut glyphs,
+        &mut assets.glyphs,
         context.transform.trans(x, y),
-        graphics).unwrap();
+        graphics);
 }
 
-pub fn draw_eyes(head: &Block, direction: &Direction, 
-            context: &Context, graphics: &mut G2d) {
-    let Coord (x, y) = head.position.to_coord();
-    let fifth = BLOCK_SIZE/5.0;
+pub fn draw_eyes(head: &Coord, direction: &Direction, 
+            viewport: &Viewport, context: &Context, graphics: &mut G2d) {
+    let Coord (x, y) = *head;
+    let fifth = viewport.block_size/5.0;
     let (eye1_x, eye1_y, eye2_x, eye2_y) = match direction {
         Direction::Up =>
             (x+fifth, y+fifth, x+fifth*3.0, y+fifth),
@@ -210,13 +834,195 @@ pub fn draw_eyes(head: &Block, direction: &Direction,
 
 
 
-fn find_resource<P: AsRef<Path>>(asset: P) -> PathBuf {
-    let mut exe_folder = std::env::current_exe()
-            .expect("Couldn't capture the executable path");
-    exe_folder.pop(); // Remove the executable name
-    let resource_path = Search::KidsThenParents(2, 2)
-            .of(exe_folder).for_folder("resources")
-            .expect("Couldn't find the resources folder");
-    resource_path.join(asset)
+fn draw_shape(shape: &Shape, coord: &Coord, assets: &Assets,
+            viewport: &Viewport, context: &Context, graphics: &mut G2d) {
+    let Coord (x, y) = *coord;
+    let size = viewport.block_size;
+    match shape {
+        Shape::Square(color) => rectangle(
+            *color, 
+            [x, y, size, size], 
+            context.transform,
+            graphics),
+        Shape::Circle(color) => ellipse(
+            *color, 
+            [x, y, size, size],
+            context.transform,
+            graphics),
+        Shape::Triangle(color) => polygon(
+            *color, 
+            &[
+                [x + size, y], 
+                [x, y + size], 
+                [x + size, y + size]
+            ], 
+            context.transform,
+            graphics),
+        Shape::Image(name) => match assets.get_texture(name) {
+            Some(texture) => {
+                let image = Image::new().rect([x, y, size, size]);
+                image.draw(texture, &DrawState::default(), context.transform, graphics);
+            },
+            // every theme has food and bonus sprites, so only a snake
+            // block without a sprite of its own ends up here
+            None => rectangle(
+                MISSING_COLOR,
+                [x, y, size, size],
+                context.transform,
+                graphics),
+        },
+    }
+}
+
+// sprites are drawn facing up, then turned around their center
+fn draw_sprite(texture: &G2dTexture, coord: &Coord, direction: &Direction,
+            color: Color, viewport: &Viewport,
+            context: &Context, graphics: &mut G2d) {
+    let Coord (x, y) = *coord;
+    let size = viewport.block_size;
+    let half = size / 2.0;
+    let angle = match direction {
+        Direction::Up => 0.0,
+        Direction::Right => 90.0,
+        Direction::Down => 180.0,
+        Direction::Left => 270.0,
+    };
+    let transform = context.transform
+            .trans(x + half, y + half)
+            .rot_deg(angle)
+            .trans(-half, -half);
+    Image::new_color(color)
+            .rect([0.0, 0.0, size, size])
+            .draw(texture, &DrawState::default(), transform, graphics);
+}
+
+fn draw_piece(look: &Look, coord: &Coord, direction: &Direction,
+            assets: &Assets, viewport: &Viewport,
+            context: &Context, graphics: &mut G2d) {
+    match look {
+        Look::Sprite(texture, color) => draw_sprite(texture, coord, direction,
+                *color, viewport, context, graphics),
+        Look::Shape(shape) => draw_shape(shape, coord, assets, viewport,
+                context, graphics),
+    }
 }
 
+// the body from the middle of the block at `position` towards `direction`,
+// `length` blocks long
+fn draw_arm(position: &Position, direction: &Direction, length: f64,
+            color: Color, viewport: &Viewport,
+            context: &Context, graphics: &mut G2d) {
+    let [x, y] = get_center(position, viewport);
+    let width = viewport.block_size * BODY_WIDTH;
+    let length = viewport.block_size * length;
+    let half = width / 2.0;
+    let rect = match direction {
+        Direction::Up => [x - half, y - length, width, length],
+        Direction::Down => [x - half, y, width, length],
+        Direction::Left => [x - length, y - half, length, width],
+        Direction::Right => [x, y - half, length, width],
+    };
+    rectangle(color, rect, context.transform, graphics);
+}
+
+// rounds the body off where it turns
+fn draw_joint(position: &Position, color: Color, viewport: &Viewport,
+            context: &Context, graphics: &mut G2d) {
+    let [x, y] = get_center(position, viewport);
+    let width = viewport.block_size * BODY_WIDTH;
+    let half = width / 2.0;
+    ellipse(color, [x - half, y - half, width, width], context.transform,
+            graphics);
+}
+
+// the body going `trail` blocks `back` from the middle of the last block,
+// then narrowing to a point half a block further
+fn draw_tail(position: &Position, back: &Direction, trail: f64, color: Color,
+            viewport: &Viewport, context: &Context, graphics: &mut G2d) {
+    draw_arm(position, back, trail, color, viewport, context, graphics);
+    let [x, y] = get_center(position, viewport);
+    let (columns, rows) = back.offset();
+    let (columns, rows) = (f64::from(columns), f64::from(rows));
+    let size = viewport.block_size;
+    let (base_x, base_y) = (x + columns * trail * size, y + rows * trail * size);
+    let half = size * BODY_WIDTH / 2.0;
+    polygon(
+        color,
+        &[
+            [base_x - rows * half, base_y + columns * half],
+            [base_x + rows * half, base_y - columns * half],
+            [base_x + columns * size / 2.0, base_y + rows * size / 2.0],
+        ],
+        context.transform,
+        graphics);
+}
+
+fn get_center(position: &Position, viewport: &Viewport) -> [f64; 2] {
+    let Coord (x, y) = position.to_coord(viewport);
+    let half = viewport.block_size / 2.0;
+    [x + half, y + half]
+}
+
+// where a block `progress` of the way along its step from `from` to `to`
+// is drawn
+fn glide(from: &Position, to: &Position, progress: f64, viewport: &Viewport)
+        -> Coord {
+    if from == to || progress >= 1.0 {
+        return to.to_coord(viewport);
+    }
+    let Coord (x, y) = from.to_coord(viewport);
+    let (columns, rows) = facing(from, to).offset();
+    let distance = progress.max(0.0) * viewport.block_size;
+    Coord (x + f64::from(columns) * distance, y + f64::from(rows) * distance)
+}
+
+// the way a block at `from` moves to get to `to`, the block ahead of it
+fn facing(from: &Position, to: &Position) -> Direction {
+    let (Position (from_x, from_y), Position (to_x, to_y)) = (from, to);
+    // blocks further apart than one step are across a wrapping edge
+    if to_x > from_x {
+        if to_x - from_x == 1 { Direction::Right } else { Direction::Left }
+    } else if to_x < from_x {
+        if from_x - to_x == 1 { Direction::Left } else { Direction::Right }
+    } else if to_y > from_y {
+        if to_y - from_y == 1 { Direction::Down } else { Direction::Up }
+    } else if from_y - to_y == 1 {
+        Direction::Up
+    } else {
+        Direction::Down
+    }
+}
+
+fn recolored(shape: &Shape, color: Color) -> Shape {
+    match shape {
+        Shape::Square(_) => Shape::Square(color),
+        Shape::Circle(_) => Shape::Circle(color),
+        Shape::Triangle(_) => Shape::Triangle(color),
+        Shape::Image(name) => Shape::Image(name.clone()),
+    }
+}
+
+fn load_font(factory: &mut GfxFactory, theme: &Theme) -> Result<Glyphs, AssetError> {
+    let settings = TextureSettings::new();
+    let glyphs = match theme.get_file_path(&theme.font) {
+        Some(path) => Glyphs::new(&path, factory.clone(), settings)
+                .map_err(|e| e.to_string()),
+        None => {
+            let bytes = Theme::get_embedded_file(&theme.font)
+                    .ok_or_else(|| ThemeError::Missing(theme.font.clone()))?;
+            Glyphs::from_bytes(bytes, factory.clone(), settings)
+                    .map_err(|_| String::from("not a TrueType font"))
+        },
+    };
+    glyphs.map_err(|reason| AssetError::Load(theme.font.clone(), reason))
+}
+
+fn load_texture(factory: &mut GfxFactory, theme: &Theme, file: &str)
+        -> Result<G2dTexture, AssetError> {
+    let bytes = theme.read_file(file)?;
+    let image = ::image::load_from_memory(&bytes)
+            .map_err(|e| AssetError::Load(String::from(file), e.to_string()))?
+            .to_rgba();
+    Texture::from_image(factory, &image, &TextureSettings::new())
+            .map_err(|e| AssetError::Load(String::from(file), format!("{:?}", e)))
+}
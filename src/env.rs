@@ -0,0 +1,574 @@
+use crate::config::Config;
+use crate::controller;
+use crate::game::{Death, Game, Status};
+use crate::grid::{Direction, Position};
+use crate::playground::Playground;
+
+
+const GRID_CHANNELS: usize = 6;
+const WINDOW_CHANNELS: usize = 3;
+const FEATURE_COUNT: usize = 13;
+
+
+/// What the agent does on a step. The first player's snake can only turn,
+/// so a U-turn or a move the way it already goes keeps it straight.
+#[derive(Clone, PartialEq)]
+pub enum Action {
+    Straight,
+    /// A quarter turn counterclockwise from where the snake heads
+    TurnLeft,
+    /// A quarter turn clockwise from where the snake heads
+    TurnRight,
+    /// Head for an absolute direction
+    Go(Direction),
+}
+
+/// How the board is turned into numbers for the agent.
+#[derive(Clone, PartialEq)]
+pub enum Encoding {
+    /// The whole board as channels of rows of columns, each block `1.0`
+    /// or `0.0`: walls, the snake's body, its head, the food, the bonus
+    /// and the other snakes.
+    Grid,
+    /// A square of blocks around the head, of an odd size so the head is
+    /// in the middle, turned so that the snake faces up: blocks that kill,
+    /// the food and the bonus. Off the board counts as a wall.
+    Window(u32),
+    /// A short vector: danger straight ahead, to the left and to the
+    /// right, the heading, where the food is, whether the bonus shows and
+    /// how much of the board the snake fills.
+    Features,
+}
+
+/// An encoded view of the game, `data` laid out row-major in `shape`.
+#[derive(Clone)]
+pub struct Observation {
+    pub shape: Vec<usize>,
+    pub data: Vec<f32>,
+}
+
+/// What a step is worth to the agent. Everything but eating and dying is
+/// off by default.
+#[derive(Clone)]
+pub struct Rewards {
+    /// For every food or bonus eaten
+    pub food: f32,
+    /// For every point scored, on top of `food`
+    pub points: f32,
+    pub death: f32,
+    /// On every step, e.g. negative to hurry the snake
+    pub step: f32,
+    /// For every block the head gets closer to the food, taken off for
+    /// every block it gets further
+    pub approach: f32,
+    /// For reaching the game's target
+    pub cleared: f32,
+}
+
+/// How the game stands after a step.
+#[derive(Clone)]
+pub struct Info {
+    pub score: u32,
+    /// Blocks of snake, head included
+    pub length: u32,
+    pub foods: u32,
+    pub ticks: u64,
+    pub death: Option<Death>,
+    /// The episode was cut at the tick limit rather than ended by the game
+    pub truncated: bool,
+}
+
+/// A reinforcement learning environment around `Game`, in the style of
+/// Gym: the agent steers the first player's snake one tick per `step`,
+/// under the very rules of the game. Other snakes of the config play as
+/// their bots, or go straight without one.
+pub struct Env {
+    config: Config,
+    encoding: Encoding,
+    rewards: Rewards,
+    tick_limit: Option<u64>,
+    game: Game,
+}
+
+
+impl Action {
+
+    /// The action numbered `index` out of the three relative ones:
+    /// straight, left and right.
+    pub fn relative(index: usize) -> Option<Action> {
+        match index {
+            0 => Some(Action::Straight),
+            1 => Some(Action::TurnLeft),
+            2 => Some(Action::TurnRight),
+            _ => None,
+        }
+    }
+
+    /// The action numbered `index` out of the four absolute ones: up,
+    /// right, down and left.
+    pub fn absolute(index: usize) -> Option<Action> {
+        let direction = match index {
+            0 => Direction::Up,
+            1 => Direction::Right,
+            2 => Direction::Down,
+            3 => Direction::Left,
+            _ => return None,
+        };
+        Some(Action::Go(direction))
+    }
+
+    /// The direction a snake heading to `heading` takes.
+    pub fn direction(&self, heading: &Direction) -> Direction {
+        match self {
+            Action::Straight => heading.clone(),
+            Action::TurnLeft => heading.turned_left(),
+            Action::TurnRight => heading.turned_right(),
+            Action::Go(direction) => direction.clone(),
+        }
+    }
+
+}
+
+
+impl Default for Rewards {
+
+    fn default() -> Rewards {
+        Rewards {
+            food: 1.0,
+            points: 0.0,
+            death: -1.0,
+            step: 0.0,
+            approach: 0.0,
+            cleared: 1.0,
+        }
+    }
+
+}
+
+
+impl Info {
+
+    fn of(game: &Game, truncated: bool) -> Info {
+        let snake = game.get_snake();
+        Info {
+            score: game.get_score(),
+            length: snake.get_length(),
+            foods: snake.get_eatings(),
+            ticks: game.get_ticks(),
+            death: game.get_death(0),
+            truncated,
+        }
+    }
+
+}
+
+
+impl Env {
+
+    /// An environment on the board of `config`, observed as a grid. Any
+    /// bot set for the first snake is left out, as the agent steers it.
+    pub fn new(config: &Config) -> Env {
+        let mut config = config.clone();
+        config.snake.bot = None;
+        let game = config.build_game_with_seed(0);
+        Env {
+            config,
+            encoding: Encoding::Grid,
+            rewards: Rewards::default(),
+            tick_limit: None,
+            game,
+        }
+    }
+
+    pub fn with_encoding(mut self, encoding: Encoding) -> Env {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn with_rewards(mut self, rewards: Rewards) -> Env {
+        self.rewards = rewards;
+        self
+    }
+
+    /// Ends episodes still going after `tick_limit` steps, as a snake
+    /// running in circles would never end one.
+    pub fn with_tick_limit(mut self, tick_limit: u64) -> Env {
+        self.tick_limit = Some(tick_limit);
+        self
+    }
+
+    /// Starts a new episode. The food of an episode only depends on its
+    /// seed and the agent's moves, so episodes can be played again.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.game = self.config.build_game_with_seed(seed);
+        self.observe()
+    }
+
+    /// Plays one tick with the agent's `action` and returns what the agent
+    /// sees next, the reward, whether the episode is over and how the game
+    /// stands. Once the episode is over, steps change nothing and are
+    /// worth nothing.
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool, Info) {
+        if self.is_done() {
+            return (self.observe(), 0.0, true, Info::of(&self.game, self.is_truncated()));
+        }
+        let score = self.game.get_score();
+        let foods = self.game.get_snake().get_eatings();
+        let distance = self.food_distance();
+
+        let heading = self.game.get_snake().get_direction().clone();
+        self.game.turn_snake(0, action.direction(&heading));
+        self.game.tick();
+
+        let eaten = self.game.get_snake().get_eatings() - foods;
+        let mut reward = self.rewards.step
+                + self.rewards.food * eaten as f32
+                + self.rewards.points * (self.game.get_score() - score) as f32;
+        if !self.game.is_alive(0) {
+            reward += self.rewards.death;
+        } else if self.game.get_status() == &Status::Cleared {
+            reward += self.rewards.cleared;
+        } else if eaten == 0 {
+            let closer = distance as f32 - self.food_distance() as f32;
+            reward += self.rewards.approach * closer;
+        }
+        let truncated = self.is_truncated();
+        (self.observe(), reward, self.is_done(), Info::of(&self.game, truncated))
+    }
+
+    /// Whether the episode is over: the snake died, the game ended or it
+    /// hit the tick limit.
+    pub fn is_done(&self) -> bool {
+        !self.game.is_alive(0) || self.game.is_finished() || self.is_truncated()
+    }
+
+    /// The encoded view of the game as it stands.
+    pub fn observe(&self) -> Observation {
+        match self.encoding {
+            Encoding::Grid => self.observe_grid(),
+            Encoding::Window(size) => self.observe_window(size),
+            Encoding::Features => self.observe_features(),
+        }
+    }
+
+    /// The shape of every observation of this environment.
+    pub fn get_observation_shape(&self) -> Vec<usize> {
+        let playground = self.game.get_playground();
+        match self.encoding {
+            Encoding::Grid => vec![GRID_CHANNELS,
+                    playground.get_height() as usize,
+                    playground.get_width() as usize],
+            Encoding::Window(size) => {
+                let size = window_size(size) as usize;
+                vec![WINDOW_CHANNELS, size, size]
+            },
+            Encoding::Features => vec![FEATURE_COUNT],
+        }
+    }
+
+    pub fn get_game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn get_encoding(&self) -> &Encoding {
+        &self.encoding
+    }
+
+    pub fn get_rewards(&self) -> &Rewards {
+        &self.rewards
+    }
+
+    fn is_truncated(&self) -> bool {
+        self.tick_limit.is_some_and(|limit| self.game.get_ticks() >= limit)
+                && self.game.is_alive(0) && !self.game.is_finished()
+    }
+
+    fn food_distance(&self) -> u32 {
+        let game = &self.game;
+        controller::distance(game, game.get_snake().get_head_position(),
+                game.get_food().get_position())
+    }
+
+    fn observe_grid(&self) -> Observation {
+        let game = &self.game;
+        let playground = game.get_playground();
+        let shape = self.get_observation_shape();
+        let (width, height) = (shape[2], shape[1]);
+        let mut data = vec![0.0; GRID_CHANNELS * width * height];
+        let mut set = |channel: usize, position: &Position| {
+            let Position (column, row) = *position;
+            data[(channel * height + row as usize) * width + column as usize] = 1.0;
+        };
+        for row in 0..playground.get_height() {
+            for column in 0..playground.get_width() {
+                let position = Position::new(column, row);
+                if playground.is_wall(&position) {
+                    set(0, &position);
+                }
+            }
+        }
+        for (player, snake) in game.get_snakes().iter().enumerate() {
+            if !game.is_alive(player) {
+                continue;
+            }
+            if player == 0 {
+                for block in snake.get_body() {
+                    set(1, block.get_position());
+                }
+                set(2, snake.get_head_position());
+            } else {
+                for block in snake.get_body() {
+                    set(5, block.get_position());
+                }
+                set(5, snake.get_head_position());
+            }
+        }
+        set(3, game.get_food().get_position());
+        if let Some(bonus) = game.get_bonus() {
+            set(4, bonus.get_position());
+        }
+        Observation { shape, data }
+    }
+
+    fn observe_window(&self, size: u32) -> Observation {
+        let game = &self.game;
+        let size = window_size(size);
+        let half = (size / 2) as i32;
+        let snake = game.get_snake();
+        let head = snake.get_head_position();
+        let forward = snake.get_direction().offset();
+        let right = snake.get_direction().turned_right().offset();
+        let food = game.get_food().get_position();
+        let bonus = game.get_bonus().map(|bonus| bonus.get_position());
+        let area = (size * size) as usize;
+        let mut data = vec![0.0; WINDOW_CHANNELS * area];
+        for row in 0..size as i32 {
+            for column in 0..size as i32 {
+                // columns go right of the head, rows behind it
+                let (x, y) = (column - half, row - half);
+                let offset = (x * right.0 - y * forward.0, x * right.1 - y * forward.1);
+                let cell = (row * size as i32 + column) as usize;
+                match board_position(game.get_playground(), head, offset) {
+                    Some(position) => {
+                        if is_deadly(game, &position) {
+                            data[cell] = 1.0;
+                        }
+                        if position == *food {
+                            data[area + cell] = 1.0;
+                        }
+                        if Some(&position) == bonus {
+                            data[2 * area + cell] = 1.0;
+                        }
+                    },
+                    None => data[cell] = 1.0,
+                }
+            }
+        }
+        Observation { shape: self.get_observation_shape(), data }
+    }
+
+    fn observe_features(&self) -> Observation {
+        let game = &self.game;
+        let playground = game.get_playground();
+        let snake = game.get_snake();
+        let head = snake.get_head_position();
+        let heading = snake.get_direction();
+        let danger = |direction: Direction| {
+            let next = playground.next_position(head, &direction);
+            flag(is_deadly(game, &next))
+        };
+        let Position (column, row) = *head;
+        let Position (food_column, food_row) = *game.get_food().get_position();
+        let border = playground.get_border_width();
+        let blocks = (playground.get_width() - border * 2)
+                * (playground.get_height() - border * 2);
+        let data = vec![
+            danger(heading.clone()),
+            danger(heading.turned_left()),
+            danger(heading.turned_right()),
+            flag(*heading == Direction::Up),
+            flag(*heading == Direction::Right),
+            flag(*heading == Direction::Down),
+            flag(*heading == Direction::Left),
+            flag(food_row < row),
+            flag(food_column > column),
+            flag(food_row > row),
+            flag(food_column < column),
+            flag(game.get_bonus().is_some()),
+            snake.get_length() as f32 / blocks.max(1) as f32,
+        ];
+        Observation { shape: self.get_observation_shape(), data }
+    }
+
+}
+
+
+
+// windows have a middle block for the head, even sizes grow by one
+fn window_size(size: u32) -> u32 {
+    size | 1
+}
+
+fn flag(on: bool) -> f32 {
+    if on { 1.0 } else { 0.0 }
+}
+
+// a wall or a living snake, tails included even though they move on with
+// the step
+fn is_deadly(game: &Game, position: &Position) -> bool {
+    game.get_playground().is_wall(position)
+            || game.get_snakes().iter().enumerate()
+                    .any(|(player, snake)| game.is_alive(player)
+                            && snake.on_position(position))
+}
+
+// the block `offset` columns and rows from `position`, or `None` off the
+// edge of a board that does not wrap
+fn board_position(playground: &Playground, position: &Position,
+        offset: (i32, i32)) -> Option<Position> {
+    let (width, height) = (playground.get_width(), playground.get_height());
+    if playground.is_wrapping() {
+        let border = playground.get_border_width();
+        let Position (column, row) = Position::new(
+                position.0 - border, position.1 - border)
+                .shifted_by(offset.0, offset.1,
+                    width - border * 2, height - border * 2);
+        return Some(Position::new(column + border, row + border));
+    }
+    let column = i64::from(position.0) + i64::from(offset.0);
+    let row = i64::from(position.1) + i64::from(offset.1);
+    if column < 0 || row < 0 || column >= i64::from(width) || row >= i64::from(height) {
+        return None;
+    }
+    Some(Position::new(column as u32, row as u32))
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // the value of `observation` at `index`, laid out as its shape says
+    fn at(observation: &Observation, index: &[usize]) -> f32 {
+        let offset = index.iter().zip(observation.shape.iter())
+                .fold(0, |offset, (index, size)| offset * size + index);
+        observation.data[offset]
+    }
+
+    // plays the first snake along shortest paths until it eats, with the
+    // reward of every step
+    fn rewards_until_eating(env: &mut Env) -> Vec<f32> {
+        let mut bot = controller::by_name("bfs").unwrap();
+        let mut rewards = Vec::new();
+        while env.get_game().get_snake().get_eatings() == 0 && rewards.len() < 500 {
+            let direction = bot.next_direction(env.get_game(), 0);
+            let (_, reward, done, _) = env.step(Action::Go(direction));
+            assert!(!done);
+            rewards.push(reward);
+        }
+        rewards
+    }
+
+    #[test]
+    fn grid_observation_shows_walls_snake_and_food() {
+        let mut env = Env::new(&Config::default());
+        let observation = env.reset(3);
+        assert_eq!(observation.shape, vec![GRID_CHANNELS, 25, 35]);
+        assert_eq!(observation.data.len(), GRID_CHANNELS * 25 * 35);
+        assert_eq!(at(&observation, &[0, 0, 0]), 1.0);
+        assert_eq!(at(&observation, &[0, 24, 34]), 1.0);
+        assert_eq!(at(&observation, &[0, 1, 1]), 0.0);
+        assert_eq!(at(&observation, &[1, 3, 4]), 1.0);
+        assert_eq!(at(&observation, &[1, 3, 3]), 1.0);
+        assert_eq!(at(&observation, &[2, 3, 5]), 1.0);
+        let Position (column, row) = *env.get_game().get_food().get_position();
+        assert_eq!(at(&observation, &[3, row as usize, column as usize]), 1.0);
+        let channel = |channel: usize| observation.data
+                .chunks(25 * 35).nth(channel).unwrap().iter().sum::<f32>();
+        assert_eq!(channel(1), 2.0);
+        assert_eq!(channel(2), 1.0);
+        assert_eq!(channel(3), 1.0);
+        assert_eq!(channel(4), 0.0);
+        assert_eq!(channel(5), 0.0);
+    }
+
+    #[test]
+    fn window_observation_faces_the_snake_up() {
+        let mut env = Env::new(&Config::default()).with_encoding(Encoding::Window(8));
+        let observation = env.reset(3);
+        // even sizes grow by one so the head is in the middle
+        assert_eq!(observation.shape, vec![WINDOW_CHANNELS, 9, 9]);
+        // the head and the body behind it
+        assert_eq!(at(&observation, &[0, 4, 4]), 1.0);
+        assert_eq!(at(&observation, &[0, 5, 4]), 1.0);
+        assert_eq!(at(&observation, &[0, 3, 4]), 0.0);
+        // the snake heads right from row 3: the top wall is on its left,
+        // then off the board
+        assert_eq!(at(&observation, &[0, 4, 1]), 1.0);
+        assert_eq!(at(&observation, &[0, 4, 0]), 1.0);
+        assert_eq!(at(&observation, &[0, 4, 2]), 0.0);
+        assert_eq!(at(&observation, &[0, 4, 8]), 0.0);
+        assert!(observation.data[81..162].iter().sum::<f32>() <= 1.0);
+    }
+
+    #[test]
+    fn features_describe_the_heading_food_and_length() {
+        let mut env = Env::new(&Config::default()).with_encoding(Encoding::Features);
+        let observation = env.reset(3);
+        assert_eq!(observation.shape, vec![FEATURE_COUNT]);
+        let data = &observation.data;
+        assert_eq!(&data[0..3], &[0.0, 0.0, 0.0]);
+        assert_eq!(&data[3..7], &[0.0, 1.0, 0.0, 0.0]);
+        let Position (column, row) = *env.get_game().get_food().get_position();
+        assert_eq!(data[7], flag(row < 3));
+        assert_eq!(data[8], flag(column > 5));
+        assert_eq!(data[9], flag(row > 3));
+        assert_eq!(data[10], flag(column < 5));
+        assert_eq!(data[11], 0.0);
+        assert_eq!(data[12], 3.0 / (33.0 * 23.0));
+    }
+
+    #[test]
+    fn eating_is_rewarded_and_plain_steps_are_not() {
+        let mut env = Env::new(&Config::default());
+        env.reset(5);
+        let rewards = rewards_until_eating(&mut env);
+        let (eaten, plain) = rewards.split_last().unwrap();
+        assert_eq!(*eaten, 1.0);
+        assert!(plain.iter().all(|reward| *reward == 0.0));
+    }
+
+    #[test]
+    fn shaping_rewards_steps_and_getting_closer() {
+        let rewards = Rewards { step: -0.25, approach: 0.5, ..Rewards::default() };
+        let mut env = Env::new(&Config::default()).with_rewards(rewards);
+        env.reset(5);
+        let rewards = rewards_until_eating(&mut env);
+        let (eaten, plain) = rewards.split_last().unwrap();
+        assert_eq!(*eaten, 0.75);
+        assert!(plain.iter().all(|reward| *reward == 0.25));
+    }
+
+    #[test]
+    fn crashing_ends_the_episode() {
+        let mut env = Env::new(&Config::default()).with_encoding(Encoding::Features);
+        env.reset(3);
+        let mut step = env.step(Action::Go(Direction::Up));
+        while !step.2 {
+            assert!(step.1 >= 0.0);
+            step = env.step(Action::Straight);
+        }
+        let (_, reward, done, info) = step;
+        assert_eq!(reward, -1.0);
+        assert!(done && env.is_done());
+        assert!(info.death == Some(Death::Wall));
+        assert_eq!(info.ticks, 3);
+        let (_, reward, done, _) = env.step(Action::TurnLeft);
+        assert_eq!(reward, 0.0);
+        assert!(done);
+    }
+
+}
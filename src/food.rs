@@ -1,10 +1,10 @@
-use piston_window::{GfxFactory,Context, G2d};
-use crate::draw::{Block, Shape, Position};
+use crate::config::{FoodConfig, BonusConfig};
+use crate::grid::{Block, Shape, Position};
 
 
-const BONUS_DISAPPEAR_TIME: f64 = 5.0;
-const FROG_IMAGE: &str = "Frog.png";
-const BONUS_IMAGE: &str = "Mouse.png";
+/// Images of food name the theme sprite drawn for them.
+pub const FOOD_SPRITE: &str = "food";
+pub const BONUS_SPRITE: &str = "bonus";
 
 
 pub struct Food {
@@ -27,18 +27,25 @@ impl Food {
     }
 
     pub fn default_food() -> Food {
-        let shape = Shape::Image(String::from(FROG_IMAGE));
-        Food::new(shape, 2, None)
+        FoodConfig::default().build()
     }
 
     pub fn default_bonus() -> Food {
-        let shape = Shape::Image(String::from(BONUS_IMAGE));
-        Food::new(shape, 10, Some(BONUS_DISAPPEAR_TIME))
+        BonusConfig::default().build()
     }
 
-    pub fn draw(&self, factory: &mut GfxFactory,
-            context: &Context, graphics: &mut G2d) {
-        self.block.draw(factory, context, graphics);
+    pub fn frog(calories: u32) -> Food {
+        let shape = Shape::Image(String::from(FOOD_SPRITE));
+        Food::new(shape, calories, None)
+    }
+
+    pub fn mouse(calories: u32, disappear_after: f64) -> Food {
+        let shape = Shape::Image(String::from(BONUS_SPRITE));
+        Food::new(shape, calories, Some(disappear_after))
+    }
+
+    pub fn get_block(&self) -> &Block {
+        &self.block
     }
 
     pub fn on_position(&self, position: &Position) -> bool {
@@ -1,47 +1,84 @@
-use rand::{thread_rng, Rng};
-use piston_window::*;
-use piston_window::types::Color;
+use std::fmt;
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_pcg::Pcg32;
+use crate::campaign::{Campaign, Target};
+use crate::config::{Config, ConfigError};
+use crate::controller::Controller;
+use crate::net::Snapshot;
 use crate::food::Food;
 use crate::playground::Playground;
 use crate::snake::{Snake};
-use crate::draw::{Position, Direction, draw_rectangle, draw_text};
+use crate::grid::{Position, Direction};
 
 
-const GAMEOVER_COLOR: Color = [0.0, 0.0, 0.0, 0.90];
-const WHITE_COLOR: Color = [1.0, 1.0, 1.0, 1.0];
-const SCORE_FONT_SIZE: u32 = 12;
-const GAMEOVER_FONT_SIZE: u32 = 20;
-const MOVE_DELAY: f64 = 0.3; //300ms
+const MOVE_DELAY_STEP: f64 = 0.02; //20ms
+const MIN_MOVE_DELAY: f64 = 0.05; //50ms
+const MAX_CATCH_UP_TICKS: u32 = 5;
 
 
 pub struct Game {
     playground: Playground,
-    snake: Snake,
+    snakes: Vec<Snake>,
     food: Food,
     bonus: Food,
-    score: u32,
+    scores: Vec<u32>,
+    alive: Vec<bool>,
+    deaths: Vec<Option<Death>>,
+    controllers: Vec<Option<Box<dyn Controller>>>,
+    winner: Option<usize>,
     status: Status,
     move_delay: f64,
+    init_move_delay: f64,
     waiting_time: f64,
     bonus_time: f64,
     show_bonus: bool,
     missed_bonus: bool,
+    ticks: u64,
+    seed: u64,
+    rng: Pcg32,
+    target: Option<Target>,
 }
 
+#[derive(Clone, PartialEq)]
 pub enum Status {
     Running,
+    Paused,
     GameOver,
+    /// The round reached its target, or a snake filled the board
+    Cleared,
+}
+
+/// What a snake crashed into.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Death {
+    Wall,
+    /// Its own body
+    Itself,
+    /// The body of another snake
+    Snake,
+    /// The head of another snake, on the same block
+    HeadOn,
 }
 
 
 impl Default for Game {
 
     fn default() -> Game {
-        let playground = Playground::default();
-        let snake = Snake::default();
-        let food = Food::default_food();
-        let bonus = Food::default_bonus();
-        Game::new(playground, snake, food, bonus, MOVE_DELAY)
+        Config::default().build_game()
+    }
+
+}
+
+
+impl fmt::Display for Death {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Death::Wall => "wall",
+            Death::Itself => "self",
+            Death::Snake => "snake",
+            Death::HeadOn => "head-on",
+        })
     }
 
 }
@@ -52,176 +89,694 @@ impl Game {
     pub fn new(playground: Playground, snake: Snake,
             food: Food, bonus: Food, move_delay: f64) 
             -> Game {
+        let seed = thread_rng().gen();
+        Game::with_seed(playground, snake, food, bonus, move_delay, seed)
+    }
+
+    /// Builds a game whose food and bonus placement is fully determined
+    /// by `seed`, so two games with the same seed and inputs play out
+    /// identically.
+    pub fn with_seed(playground: Playground, snake: Snake,
+            food: Food, bonus: Food, move_delay: f64, seed: u64) 
+            -> Game {
         let mut game = Game {
             playground,
-            snake,
+            snakes: vec![snake],
             food,
             bonus,
             move_delay,
-            score: 0,
+            init_move_delay: move_delay,
+            scores: vec![0],
+            alive: vec![true],
+            deaths: vec![None],
+            controllers: vec![None],
+            winner: None,
             status: Status::Running,
             waiting_time: 0.0,
             bonus_time: 0.0,
             show_bonus: false,
             missed_bonus: false,
+            ticks: 0,
+            seed,
+            rng: Pcg32::seed_from_u64(seed),
+            target: None,
         };
-        // randomize food position
-        game.food.set_position(game.get_random_position());
+        game.start_round();
         game
     }
 
-    pub fn draw(&self, factory: &mut GfxFactory, 
-            context: &Context, graphics: &mut G2d) {
-        self.playground.draw(factory, context, graphics);
-        self.food.draw(factory, context, graphics);
-        if self.show_bonus {
-            self.bonus.draw(factory, context, graphics);
-        }
-        self.snake.draw(factory, context, graphics);
-        draw_rectangle(
-            &Position (0, self.playground.get_height()),
-            self.playground.get_width(),
-            2,
-            [0.80, 0.30, 0.30, 1.0], //border color
-            context,
-            graphics);
-        draw_text(
-            &format!("Score: {}", &self.score), 
-            &Position (2, self.playground.get_height()+1), 
-            WHITE_COLOR, 
-            SCORE_FONT_SIZE, 
-            factory.clone(), 
-            context, 
-            graphics);
-
-        if let Status::GameOver = self.status {
-            draw_rectangle(
-                &Position (0, 0), 
-                self.playground.get_width(), 
-                self.playground.get_height()+2, 
-                GAMEOVER_COLOR, 
-                context, 
-                graphics);
-            draw_text(
-                "Game Over", 
-                &Position (13, 12), 
-                WHITE_COLOR, 
-                GAMEOVER_FONT_SIZE, 
-                factory.clone(), 
-                context, 
-                graphics);
-        }
-    }
-
-    pub fn key_pressed(&mut self, key: Key) {
-        if let Status::Running = self.status {
-            let dir = match key {
-                Key::Up => Some(Direction::Up),
-                Key::Down => Some(Direction::Down),
-                Key::Left => Some(Direction::Left),
-                Key::Right => Some(Direction::Right),
-                _ => None,
-            };
-            if let Some(direction) = &dir {
-                let snake_direction = self.snake.get_direction();
-                if direction != snake_direction &&
-                        *direction != snake_direction.opposite() {
-                    self.update_snake(dir);
+    /// The game `config` describes, or the current stage of `campaign`
+    /// built on top of it, with its food placed from `seed` when given.
+    pub fn from_config(config: &Config, campaign: Option<&Campaign>,
+            seed: Option<u64>) -> Result<Game, ConfigError> {
+        let campaign = match campaign {
+            Some(campaign) => campaign,
+            None => return Ok(match seed {
+                Some(seed) => config.build_game_with_seed(seed),
+                None => config.build_game(),
+            }),
+        };
+        let game = match seed {
+            Some(seed) => campaign.build_game_with_seed(config, seed),
+            None => campaign.build_game(config),
+        };
+        game.map_err(|e| ConfigError::Invalid(format!("campaign stage {}: {}",
+                campaign.get_stage().get_name(), e)))
+    }
+
+    /// Adds a snake for each further player. The first player keeps the
+    /// snake the game was built with.
+    pub fn with_rivals(mut self, rivals: Vec<Snake>) -> Game {
+        for snake in rivals {
+            self.snakes.push(snake);
+            self.scores.push(0);
+            self.alive.push(true);
+            self.deaths.push(None);
+            self.controllers.push(None);
+        }
+        self.start_round();
+        self
+    }
+
+    /// Buffers a turn for the first player's snake.
+    pub fn turn(&mut self, direction: Direction) {
+        self.turn_snake(0, direction);
+    }
+
+    /// Buffers a turn for the snake of `player`; queued turns are
+    /// consumed one per `tick`. Snakes driven by a controller ignore
+    /// turns from outside.
+    pub fn turn_snake(&mut self, player: usize, direction: Direction) {
+        match self.status {
+            Status::Running | Status::Paused => {
+                if self.get_controller(player).is_some() {
+                    return;
                 }
-            }
-        } else if let Key::Return = key {
-            self.restart();
+                if let Some(snake) = self.snakes.get_mut(player) {
+                    snake.queue_turn(direction);
+                }
+            },
+            Status::GameOver | Status::Cleared => (),
+        }
+    }
+
+    /// Sets what the round has to reach to be cleared, as in a campaign
+    /// stage; without a target the round only ends with the snake.
+    pub fn set_target(&mut self, target: Option<Target>) {
+        self.target = target;
+    }
+
+    /// Hands the snake of `player` over to `controller`, or back to the
+    /// player with `None`.
+    pub fn set_controller(&mut self, player: usize,
+            controller: Option<Box<dyn Controller>>) {
+        if let Some(slot) = self.controllers.get_mut(player) {
+            *slot = controller;
+        }
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.status = match self.status {
+            Status::Running => Status::Paused,
+            Status::Paused => Status::Running,
+            Status::GameOver => Status::GameOver,
+            Status::Cleared => Status::Cleared,
+        };
+    }
+
+    pub fn pause(&mut self) {
+        if let Status::Running = self.status {
+            self.status = Status::Paused;
         }
     }
 
+    /// Runs a single tick while paused, for stepping through a game.
+    pub fn single_step(&mut self) {
+        if let Status::Paused = self.status {
+            self.tick();
+        }
+    }
+
+    /// Wall-clock adapter around `tick`: accumulates the elapsed time and
+    /// runs one tick for every `move_delay` seconds that have passed.
+    /// Nothing advances while the game is paused, and after a long stall
+    /// (a dragged window, a debugger) only a few ticks are caught up on.
     pub fn update(&mut self, delta_time: f64) {
+        if let Status::Paused | Status::GameOver | Status::Cleared =
+                self.status {
+            return;
+        }
         self.waiting_time += delta_time;
-        self.bonus_time += delta_time;
-        if let Status::GameOver = self.status {
+        let mut ticks = 0;
+        while self.waiting_time >= self.move_delay {
+            if ticks == MAX_CATCH_UP_TICKS {
+                self.waiting_time %= self.move_delay;
+                break;
+            }
+            self.waiting_time -= self.move_delay;
+            self.tick();
+            ticks += 1;
+            if self.is_finished() {
+                self.waiting_time = 0.0;
+                break;
+            }
+        }
+    }
+
+    /// Advances the simulation by exactly one step, consuming at most one
+    /// buffered turn per snake. All snakes move before any collision is
+    /// checked, so snakes that crash on the same tick die together.
+    pub fn tick(&mut self) {
+        if self.is_finished() {
             return;
-        } else if self.snake.bite_itself() ||
-                self.snake.hit_walls_of(&self.playground) {
+        }
+        self.ticks += 1;
+
+        self.ask_controllers();
+        for (snake, alive) in self.snakes.iter_mut().zip(self.alive.iter()) {
+            if *alive {
+                snake.step(&self.playground);
+            }
+        }
+        let mut filled_by = None;
+        for player in 0..self.snakes.len() {
+            if self.alive[player] && self.try_eating(player) {
+                filled_by = Some(player);
+            }
+        }
+        // every crash is found before any snake is taken off the board, so
+        // a snake dying this tick is still in the way of the others
+        let crashed: Vec<(usize, Death)> = (0..self.snakes.len())
+                .filter(|&player| self.alive[player])
+                .filter_map(|player| self.crashed(player).map(|death| (player, death)))
+                .collect();
+        for (player, death) in crashed.iter() {
+            self.alive[*player] = false;
+            self.deaths[*player] = Some(*death);
+        }
+        let crashed: Vec<usize> = crashed.into_iter()
+                .map(|(player, _)| player)
+                .collect();
+        let survivors: Vec<usize> = (0..self.snakes.len())
+                .filter(|&player| self.alive[player])
+                .collect();
+        if survivors.is_empty()
+                || (self.snakes.len() > 1 && survivors.len() == 1) {
+            self.winner = self.find_winner(&survivors, &crashed);
             self.status = Status::GameOver;
+            return;
+        }
+        if let Some(true) = self.target.as_ref()
+                .map(|target| target.is_reached(self)) {
+            self.status = Status::Cleared;
+            return;
+        }
+        // with no block left for the food, the board is won
+        if let Some(player) = filled_by {
+            self.winner = Some(player).filter(|_| self.snakes.len() > 1);
+            self.status = Status::Cleared;
+            return;
         }
 
         if self.show_bonus {
+            self.bonus_time += self.move_delay;
             if self.bonus_time > self.bonus.get_disappear_after().unwrap() {
                 self.show_bonus = false;
                 self.missed_bonus = true;
             }
-        } else if self.snake.worth_bonus() && !self.missed_bonus {
-            let new_pos = self.get_random_position();
-            self.bonus.set_position(new_pos);
-            self.show_bonus = true;
-            self.bonus_time = 0.0;
-        }
-
-        if self.waiting_time > self.move_delay {
-            self.update_snake(None);
+        } else if !self.missed_bonus && self.snakes.iter().zip(self.alive.iter())
+                .any(|(snake, alive)| *alive && snake.worth_bonus()) {
+            if let Some(new_pos) = self.get_random_position() {
+                self.bonus.set_position(new_pos);
+                self.show_bonus = true;
+                self.bonus_time = 0.0;
+            }
         }
     }
 
-    fn update_snake(&mut self, dir: Option<Direction>) {
-        self.snake.step(dir);
-        self.try_eating();
-        self.waiting_time = 0.0;
-    }
-
-    fn try_eating(&mut self) {
-        if self.food.get_position() == self.snake.get_head_position() {
-            self.snake.eat();
-            self.score += self.food.get_calories();
-            self.food.set_position(self.get_random_position());
+    // whether the snake of `player` ate the food on the last free block
+    fn try_eating(&mut self, player: usize) -> bool {
+        let snake = &mut self.snakes[player];
+        let mut filled = false;
+        if self.food.get_position() == snake.get_head_position() {
+            snake.eat();
+            self.scores[player] += self.food.get_calories();
+            // a snake filling the whole board leaves the food where it is
+            match self.get_random_position() {
+                Some(new_pos) => self.food.set_position(new_pos),
+                None => filled = true,
+            }
             self.missed_bonus = false;
         } else if self.show_bonus && 
-                self.bonus.on_position(self.snake.get_head_position()) {
-            self.snake.eat();
-            self.score += self.bonus.get_calories();
+                self.bonus.on_position(snake.get_head_position()) {
+            snake.eat();
+            self.scores[player] += self.bonus.get_calories();
             self.show_bonus = false;
             // increase game speed
-            if self.move_delay > 0.0 {
-                self.move_delay -= 0.02;
+            self.move_delay = (self.move_delay - MOVE_DELAY_STEP)
+                    .max(MIN_MOVE_DELAY);
+        }
+        filled
+    }
+
+    fn ask_controllers(&mut self) {
+        // taken out for the time being, so controllers can look at the game
+        let mut controllers = std::mem::take(&mut self.controllers);
+        for (player, controller) in controllers.iter_mut().enumerate() {
+            if let (Some(controller), true) = (controller, self.alive[player]) {
+                let direction = controller.next_direction(self, player);
+                self.snakes[player].queue_turn(direction);
             }
         }
+        self.controllers = controllers;
+    }
+
+    // running into a wall, itself or any other living snake, head to head
+    // included
+    fn crashed(&self, player: usize) -> Option<Death> {
+        let snake = &self.snakes[player];
+        let head = snake.get_head_position();
+        if snake.hit_walls_of(&self.playground) {
+            return Some(Death::Wall);
+        }
+        if snake.bite_itself() {
+            return Some(Death::Itself);
+        }
+        let rivals = self.snakes.iter().enumerate()
+                .filter(|(other, _)| *other != player && self.alive[*other])
+                .map(|(_, rival)| rival);
+        for rival in rivals {
+            if rival.get_head_position() == head {
+                return Some(Death::HeadOn);
+            }
+            if rival.on_position(head) {
+                return Some(Death::Snake);
+            }
+        }
+        None
+    }
+
+    // the last snake standing wins; when the last ones crash together the
+    // best score among them wins, and a tie is a draw
+    fn find_winner(&self, survivors: &[usize], crashed: &[usize])
+            -> Option<usize> {
+        if self.snakes.len() == 1 {
+            return None;
+        }
+        if let [survivor] = survivors {
+            return Some(*survivor);
+        }
+        let best = crashed.iter().map(|&player| self.scores[player]).max()?;
+        let mut best_players = crashed.iter()
+                .filter(|&&player| self.scores[player] == best);
+        match (best_players.next(), best_players.next()) {
+            (Some(player), None) => Some(*player),
+            _ => None,
+        }
     }
 
-    fn restart(&mut self) {
-        self.snake.reset();
+    /// Takes over the state the server sent, for a client that only
+    /// renders the game.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.ticks = snapshot.ticks;
+        self.status = snapshot.status.clone();
+        self.winner = snapshot.winner;
+        self.move_delay = snapshot.move_delay;
+        self.food.set_position(snapshot.food.clone());
+        self.show_bonus = snapshot.bonus.is_some();
+        if let Some(position) = &snapshot.bonus {
+            self.bonus.set_position(position.clone());
+        }
+        let players = self.snakes.iter_mut()
+                .zip(self.scores.iter_mut())
+                .zip(self.alive.iter_mut());
+        for (((snake, score), alive), state) in players.zip(snapshot.snakes.iter()) {
+            snake.restore(&state.blocks, state.direction.clone());
+            *score = state.score;
+            *alive = state.alive;
+        }
+    }
+
+    /// Starts a new round seeded from the current round's RNG, so a whole
+    /// session stays reproducible from the first seed.
+    pub fn restart(&mut self) {
+        self.seed = self.rng.gen();
+        self.start_round();
+    }
+
+    fn start_round(&mut self) {
+        self.rng = Pcg32::seed_from_u64(self.seed);
+        for snake in self.snakes.iter_mut() {
+            snake.reset();
+        }
+        self.scores.iter_mut().for_each(|score| *score = 0);
+        self.alive.iter_mut().for_each(|alive| *alive = true);
+        self.deaths.iter_mut().for_each(|death| *death = None);
+        self.winner = None;
         self.status = Status::Running;
-        self.move_delay = MOVE_DELAY;
+        self.move_delay = self.init_move_delay;
         self.waiting_time = 0.0;
-        self.score = 0;
+        self.bonus_time = 0.0;
         self.show_bonus = false;
-        self.food.set_position(self.get_random_position());
-        self.bonus.set_position(self.get_random_position());
+        self.missed_bonus = false;
+        self.ticks = 0;
+        // forget the previous round's food so every round plays out
+        // exactly like a fresh game built with the same seed
+        self.food.set_position(Position::new(0, 0));
+        self.bonus.set_position(Position::new(0, 0));
+        if let Some(food_pos) = self.get_random_position() {
+            self.food.set_position(food_pos);
+        }
     }
 
-    fn get_random_position(&self) -> Position {
-        let mut rng = thread_rng();
+    // a free block for the food or the bonus, or `None` once the snakes
+    // cover every block they can spawn on
+    fn get_random_position(&mut self) -> Option<Position> {
         let border = self.playground.get_border_width();
         let width = self.playground.get_width();
         let height = self.playground.get_height();
-        let mut column = rng.gen_range(border, width - border - 1);
-        let mut row = rng.gen_range(border, height - border - 1);
-        let mut new_pos = Position (column, row);
+        let food_zone = self.playground.get_food_zone();
+        let any_free = if food_zone.is_empty() {
+            (border..width - border)
+                    .flat_map(|column| (border..height - border)
+                            .map(move |row| Position (column, row)))
+                    .any(|position| self.is_free(&position))
+        } else {
+            food_zone.iter().any(|position| self.is_free(position))
+        };
+        if !any_free {
+            return None;
+        }
+        loop {
+            let new_pos = if food_zone.is_empty() {
+                let column = self.rng.gen_range(border, width - border);
+                let row = self.rng.gen_range(border, height - border);
+                Position (column, row)
+            } else {
+                food_zone[self.rng.gen_range(0, food_zone.len())].clone()
+            };
+            if self.is_free(&new_pos) {
+                return Some(new_pos);
+            }
+        }
+    }
+
+    fn is_free(&self, position: &Position) -> bool {
+        let on_snake = self.snakes.iter().zip(self.alive.iter())
+                .any(|(snake, alive)| *alive && snake.on_position(position));
+        !on_snake
+                && !self.food.on_position(position)
+                && !self.bonus.on_position(position)
+                && !self.playground.is_wall(position)
+    }
+
+    pub fn get_playground(&self) -> &Playground {
+        &self.playground
+    }
+
+    /// The first player's snake.
+    pub fn get_snake(&self) -> &Snake {
+        &self.snakes[0]
+    }
+
+    pub fn get_snakes(&self) -> &[Snake] {
+        &self.snakes
+    }
+
+    pub fn get_player_count(&self) -> usize {
+        self.snakes.len()
+    }
+
+    pub fn is_alive(&self, player: usize) -> bool {
+        self.alive[player]
+    }
+
+    /// What the snake of `player` crashed into, once it is dead.
+    pub fn get_death(&self, player: usize) -> Option<Death> {
+        self.deaths[player]
+    }
+
+    pub fn get_controller(&self, player: usize) -> Option<&dyn Controller> {
+        self.controllers.get(player)
+                .and_then(|controller| controller.as_deref())
+    }
+
+    pub fn get_food(&self) -> &Food {
+        &self.food
+    }
 
-        while self.snake.on_position(&new_pos) 
-                || self.food.on_position(&new_pos)
-                || self.bonus.on_position(&new_pos)
-        {
-            column = rng.gen_range(border, width - border - 1);
-            row = rng.gen_range(border, height - border - 1);
-            new_pos = Position (column, row);
+    pub fn get_bonus(&self) -> Option<&Food> {
+        if self.show_bonus {
+            Some(&self.bonus)
+        } else {
+            None
         }
-        new_pos
     }
 
-    pub fn get_size(&self) -> [f64; 2] {
-        let width = self.playground.get_width();
-        let height = self.playground.get_height() + 2;
-        Position::new(width, height)
-            .to_coord()
-            .as_array()
+    pub fn get_bonus_food(&self) -> &Food {
+        &self.bonus
+    }
+
+    /// The first player's score.
+    pub fn get_score(&self) -> u32 {
+        self.scores[0]
+    }
+
+    pub fn get_scores(&self) -> &[u32] {
+        &self.scores
+    }
+
+    /// The player who won a finished game of several snakes, or `None` for
+    /// a draw or a single player game.
+    pub fn get_winner(&self) -> Option<usize> {
+        self.winner
+    }
+
+    /// Whether the round is over, lost or cleared.
+    pub fn is_finished(&self) -> bool {
+        match self.status {
+            Status::GameOver | Status::Cleared => true,
+            Status::Running | Status::Paused => false,
+        }
+    }
+
+    pub fn get_target(&self) -> Option<&Target> {
+        self.target.as_ref()
+    }
+
+    pub fn get_status(&self) -> &Status {
+        &self.status
+    }
+
+    pub fn get_move_delay(&self) -> f64 {
+        self.move_delay
+    }
+
+    pub fn get_init_move_delay(&self) -> f64 {
+        self.init_move_delay
     }
 
-}
\ No newline at end of file
+    /// How far the game is from its last tick to the next, from 0 right
+    /// after a tick to 1 when the next one is due. Games that are over or
+    /// have not moved yet stay at 1.
+    pub fn get_step_progress(&self) -> f64 {
+        match self.status {
+            Status::Running | Status::Paused if self.ticks > 0 =>
+                (self.waiting_time / self.move_delay).min(1.0),
+            _ => 1.0,
+        }
+    }
+
+    pub fn get_ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::config::SnakeConfig;
+    use crate::snake::SNAKE_TURN_BUFFER;
+
+    // a seeded game with one snake per `(head, direction, length)`
+    fn game_of(snakes: &[(Position, Direction, u32)]) -> Game {
+        let mut snakes = snakes.iter().map(|(position, direction, length)| {
+            SnakeConfig {
+                init_position: position.clone(),
+                init_direction: direction.clone(),
+                init_length: *length,
+                ..SnakeConfig::default()
+            }.build()
+        });
+        let first = snakes.next().unwrap();
+        Game::with_seed(Playground::default(), first, Food::default_food(),
+                Food::default_bonus(), 0.3, 1)
+            .with_rivals(snakes.collect())
+    }
+
+    #[test]
+    fn snake_steps_once_its_move_delay_is_up() {
+        let mut game = Game::default();
+        let Position (column, row) = game.get_snake().get_head_position().clone();
+        game.update(0.2);
+        assert!(*game.get_snake().get_head_position() == Position (column, row));
+        game.update(0.2);
+        assert!(*game.get_snake().get_head_position() == Position (column + 1, row));
+    }
+
+    #[test]
+    fn a_long_stall_only_catches_up_a_few_ticks() {
+        let mut game = Game::default();
+        let Position (column, row) = game.get_snake().get_head_position().clone();
+        game.update(60.0);
+        assert!(matches!(game.get_status(), Status::Running));
+        assert!(*game.get_snake().get_head_position()
+                == Position (column + MAX_CATCH_UP_TICKS, row));
+        game.update(0.0);
+        assert!(*game.get_snake().get_head_position()
+                == Position (column + MAX_CATCH_UP_TICKS, row));
+    }
+
+    #[test]
+    fn snake_crashes_into_the_border() {
+        let mut game = Game::default();
+        for _ in 0..200 {
+            game.update(0.2);
+        }
+        assert!(matches!(game.get_status(), Status::GameOver));
+    }
+
+    #[test]
+    fn food_lands_inside_the_border_and_off_the_snake() {
+        for _ in 0..100 {
+            let game = Game::default();
+            let playground = game.get_playground();
+            let border = playground.get_border_width();
+            let Position (column, row) = game.get_food().get_position().clone();
+            assert!(column >= border && column < playground.get_width() - border);
+            assert!(row >= border && row < playground.get_height() - border);
+            assert!(!game.get_snake().on_position(game.get_food().get_position()));
+        }
+    }
+
+    #[test]
+    fn food_placement_follows_the_seed() {
+        let placements = |seed: u64| {
+            let mut game = Game::with_seed(Playground::default(),
+                    Snake::default(), Food::default_food(),
+                    Food::default_bonus(), 0.3, seed);
+            (0..20).map(|_| {
+                game.restart();
+                game.get_food().get_position().clone()
+            }).collect::<Vec<Position>>()
+        };
+        let placed = placements(42);
+        assert!(placed == placements(42));
+        assert!(placed != placements(43));
+    }
+
+    #[test]
+    fn turns_are_checked_against_the_last_queued_one() {
+        let mut game = Game::default();
+        assert!(*game.get_snake().get_direction() == Direction::Right);
+        game.turn(Direction::Left);
+        game.turn(Direction::Up);
+        game.turn(Direction::Down);
+        // a U-turn of the current direction, but not of the queued one
+        game.turn(Direction::Left);
+        game.tick();
+        assert!(*game.get_snake().get_direction() == Direction::Up);
+        game.tick();
+        assert!(*game.get_snake().get_direction() == Direction::Left);
+        game.tick();
+        assert!(*game.get_snake().get_direction() == Direction::Left);
+    }
+
+    #[test]
+    fn turn_buffer_is_capped() {
+        let mut game = Game::default();
+        let quarter_turns = [Direction::Up, Direction::Left, Direction::Down,
+                Direction::Right];
+        let turns: Vec<Direction> = quarter_turns.iter().cloned().cycle()
+                .take(SNAKE_TURN_BUFFER + 2)
+                .collect();
+        for direction in turns.iter() {
+            game.turn(direction.clone());
+        }
+        for direction in turns.iter().take(SNAKE_TURN_BUFFER) {
+            game.tick();
+            assert!(game.get_snake().get_direction() == direction);
+        }
+        game.tick();
+        assert!(*game.get_snake().get_direction() == turns[SNAKE_TURN_BUFFER - 1]);
+    }
+
+    #[test]
+    fn snakes_meeting_head_on_both_die_in_a_draw() {
+        let mut game = game_of(&[
+            (Position (10, 10), Direction::Right, 2),
+            (Position (14, 10), Direction::Left, 2),
+        ]);
+        game.tick();
+        assert!(game.is_alive(0) && game.is_alive(1));
+        game.tick();
+        assert!(game.get_death(0) == Some(Death::HeadOn));
+        assert!(game.get_death(1) == Some(Death::HeadOn));
+        assert!(matches!(game.get_status(), Status::GameOver));
+        assert_eq!(game.get_winner(), None);
+    }
+
+    #[test]
+    fn running_into_a_body_leaves_the_other_snake_the_winner() {
+        let mut game = game_of(&[
+            (Position (10, 10), Direction::Right, 2),
+            (Position (12, 9), Direction::Up, 4),
+        ]);
+        game.tick();
+        assert!(game.is_alive(0) && game.is_alive(1));
+        game.tick();
+        assert!(game.get_death(0) == Some(Death::Snake));
+        assert!(game.is_alive(1));
+        assert!(matches!(game.get_status(), Status::GameOver));
+        assert_eq!(game.get_winner(), Some(1));
+    }
+
+    #[test]
+    fn last_survivor_of_three_wins() {
+        let mut game = game_of(&[
+            (Position (10, 1), Direction::Up, 2),
+            (Position (20, 3), Direction::Up, 2),
+            (Position (10, 15), Direction::Right, 2),
+        ]);
+        game.tick();
+        assert!(game.get_death(0) == Some(Death::Wall));
+        assert!(matches!(game.get_status(), Status::Running));
+        assert_eq!(game.get_winner(), None);
+        game.tick();
+        game.tick();
+        assert!(game.get_death(1) == Some(Death::Wall));
+        assert!(matches!(game.get_status(), Status::GameOver));
+        assert_eq!(game.get_winner(), Some(2));
+    }
+
+    #[test]
+    fn a_snake_dying_on_a_tick_still_blocks_the_others_on_it() {
+        let mut game = game_of(&[
+            (Position (10, 1), Direction::Up, 3),
+            (Position (9, 3), Direction::Right, 2),
+        ]);
+        game.tick();
+        assert!(game.get_death(0) == Some(Death::Wall));
+        assert!(game.get_death(1) == Some(Death::Snake));
+        assert!(matches!(game.get_status(), Status::GameOver));
+    }
+
+}
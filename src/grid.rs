@@ -0,0 +1,169 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+
+
+pub type Color = [f32; 4];
+
+// what images are tinted with, which leaves their own colors
+const WHITE_COLOR: Color = [1.0, 1.0, 1.0, 1.0];
+
+
+pub struct Block {
+    position: Position,
+    shape: Shape,
+}
+
+#[derive(Clone)]
+pub enum Shape {
+    Square(Color),
+    Circle(Color),
+    Triangle(Color),
+    Image(String),
+}
+
+
+#[derive(Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Position (pub u32, pub u32);
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+
+impl Direction {
+
+    /// The column and row offsets of one step in this direction.
+    pub fn offset(&self) -> (i32, i32) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    /// The direction after a quarter turn counterclockwise.
+    pub fn turned_left(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    /// The direction after a quarter turn clockwise.
+    pub fn turned_right(&self) -> Direction {
+        self.turned_left().opposite()
+    }
+
+}
+
+
+impl fmt::Display for Direction {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Left => "left",
+            Direction::Right => "right",
+        };
+        f.write_str(name)
+    }
+
+}
+
+
+impl FromStr for Direction {
+
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Direction, String> {
+        match s {
+            "up" => Ok(Direction::Up),
+            "down" => Ok(Direction::Down),
+            "left" => Ok(Direction::Left),
+            "right" => Ok(Direction::Right),
+            _ => Err(format!("unknown direction '{}'", s)),
+        }
+    }
+
+}
+
+
+impl Position {
+
+    pub fn new(columns: u32, rows: u32) -> Position {
+        Position (columns, rows)
+    }
+
+    /// Moves the position on a `width` by `height` board whose opposite
+    /// edges are joined, so stepping off one edge comes back on the other.
+    pub fn shifted_by(&self, columns: i32, rows: i32,
+            width: u32, height: u32) -> Position {
+        let column = (i64::from(self.0) + i64::from(columns))
+                .rem_euclid(i64::from(width));
+        let row = (i64::from(self.1) + i64::from(rows))
+                .rem_euclid(i64::from(height));
+        Position::new(column as u32, row as u32)
+    }
+
+}
+
+
+impl Shape {
+
+    /// The color the shape is filled or tinted with.
+    pub fn get_color(&self) -> Color {
+        match self {
+            Shape::Square(color) | Shape::Circle(color) | Shape::Triangle(color) =>
+                *color,
+            Shape::Image(_) => WHITE_COLOR,
+        }
+    }
+
+}
+
+
+impl Block {
+
+    pub fn new(position: Position, shape: Shape) -> Block {
+        Block {
+            position,
+            shape,
+        }
+    }
+
+    pub fn get_position(&self) -> &Position {
+        &self.position
+    }
+
+    pub fn set_position(&mut self, position: Position) {
+        self.position = position;
+    }
+
+    pub fn get_shape(&self) -> &Shape {
+        &self.shape
+    }
+
+    pub fn set_shape(&mut self, shape: Shape) {
+        self.shape = shape;
+    }
+
+}
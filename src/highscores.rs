@@ -0,0 +1,313 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::config::Config;
+use crate::game::Game;
+
+
+const SCORES_MAGIC: &str = "rusty-snake-scores";
+const SCORES_VERSION: u32 = 1;
+const SCORES_FILE: &str = "highscores.txt";
+const HIGH_SCORE_COUNT: usize = 5;
+const MAX_NAME_LENGTH: usize = 12;
+
+
+#[derive(Clone)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u32,
+    pub date: String,
+    pub width: u32,
+    pub height: u32,
+    pub move_delay: f64,
+}
+
+/// The top scores, kept sorted from best to worst.
+pub struct HighScores {
+    entries: Vec<HighScore>,
+    capacity: usize,
+    path: Option<PathBuf>,
+}
+
+
+impl HighScore {
+
+    /// Builds an entry for the round that just ended in `game`, dated
+    /// today.
+    pub fn from_game(name: &str, game: &Game) -> HighScore {
+        let playground = game.get_playground();
+        HighScore {
+            name: clean_name(name),
+            score: game.get_score(),
+            date: today(),
+            width: playground.get_width(),
+            height: playground.get_height(),
+            move_delay: game.get_init_move_delay(),
+        }
+    }
+
+    fn parse(line: &str) -> Option<HighScore> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 6 {
+            return None;
+        }
+        Some(HighScore {
+            score: fields[0].parse().ok()?,
+            name: clean_name(fields[1]),
+            date: String::from(fields[2]),
+            width: fields[3].parse().ok()?,
+            height: fields[4].parse().ok()?,
+            move_delay: fields[5].parse().ok()?,
+        })
+    }
+
+    fn to_line(&self) -> String {
+        format!("{}\t{}\t{}\t{}\t{}\t{}", self.score, self.name, self.date,
+                self.width, self.height, self.move_delay)
+    }
+
+}
+
+
+impl Default for HighScores {
+
+    fn default() -> HighScores {
+        match Config::data_path(SCORES_FILE) {
+            Some(path) => HighScores::load(path),
+            None => HighScores::new(None),
+        }
+    }
+
+}
+
+
+impl HighScores {
+
+    pub fn new(path: Option<PathBuf>) -> HighScores {
+        HighScores {
+            entries: Vec::new(),
+            capacity: HIGH_SCORE_COUNT,
+            path,
+        }
+    }
+
+    /// Loads the table stored at `path`. A missing or unreadable file
+    /// gives an empty table, and corrupted lines are skipped, so a bad
+    /// file never stops the game from starting.
+    pub fn load<P: AsRef<Path>>(path: P) -> HighScores {
+        let path = path.as_ref();
+        let mut scores = HighScores::new(Some(path.to_path_buf()));
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                return scores;
+            },
+            Err(e) => {
+                eprintln!("Ignoring unreadable high scores {}: {}",
+                        path.display(), e);
+                return scores;
+            },
+        };
+
+        let mut lines = text.lines();
+        let header = format!("{} {}", SCORES_MAGIC, SCORES_VERSION);
+        if lines.next().map(str::trim) != Some(header.as_str()) {
+            eprintln!("Ignoring corrupted high scores {}", path.display());
+            return scores;
+        }
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            match HighScore::parse(line) {
+                Some(entry) => {
+                    scores.add(entry);
+                },
+                None => eprintln!("Skipping corrupted high score '{}'", line),
+            }
+        }
+        scores
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder)?;
+        }
+        let mut text = format!("{} {}\n", SCORES_MAGIC, SCORES_VERSION);
+        for entry in self.entries.iter() {
+            text.push_str(&entry.to_line());
+            text.push('\n');
+        }
+        // write next to the table first so a crash never leaves it half
+        // written
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, text)?;
+        fs::rename(&temp_path, path)
+    }
+
+    /// Whether `score` would make it into the table.
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0 && (self.entries.len() < self.capacity
+                || self.entries.iter().any(|entry| score > entry.score))
+    }
+
+    /// Inserts `entry` and returns its rank, or `None` if it did not
+    /// make it into the table.
+    pub fn add(&mut self, entry: HighScore) -> Option<usize> {
+        if !self.qualifies(entry.score) {
+            return None;
+        }
+        let rank = self.entries.iter()
+                .position(|other| entry.score > other.score)
+                .unwrap_or(self.entries.len());
+        self.entries.insert(rank, entry);
+        self.entries.truncate(self.capacity);
+        Some(rank)
+    }
+
+    pub fn get_entries(&self) -> &[HighScore] {
+        &self.entries
+    }
+
+    pub fn get_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+}
+
+
+
+fn clean_name(name: &str) -> String {
+    let name: String = name.chars()
+            .filter(|c| !c.is_control())
+            .take(MAX_NAME_LENGTH)
+            .collect();
+    let name = name.trim();
+    if name.is_empty() {
+        String::from("Anonymous")
+    } else {
+        String::from(name)
+    }
+}
+
+pub fn is_name_full(name: &str) -> bool {
+    name.chars().count() >= MAX_NAME_LENGTH
+}
+
+fn today() -> String {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+    date_of(seconds)
+}
+
+// the `YYYY-MM-DD` date `seconds` after the Unix epoch
+fn date_of(seconds: u64) -> String {
+    // civil-from-days conversion of the days since 1970-01-01
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+            - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era
+            - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::env;
+    use std::process;
+
+    fn entry(name: &str, score: u32) -> HighScore {
+        HighScore {
+            name: String::from(name),
+            score,
+            date: String::from("2024-02-29"),
+            width: 35,
+            height: 25,
+            move_delay: 0.3,
+        }
+    }
+
+    fn scores_of(table: &HighScores) -> Vec<u32> {
+        table.get_entries().iter().map(|entry| entry.score).collect()
+    }
+
+    #[test]
+    fn qualifies_needs_points_and_a_free_or_beaten_rank() {
+        let mut table = HighScores::new(None);
+        assert!(!table.qualifies(0));
+        assert!(table.qualifies(1));
+        for score in [10, 20, 30, 40, 50].iter() {
+            table.add(entry("a", *score));
+        }
+        assert!(!table.qualifies(10));
+        assert!(table.qualifies(11));
+    }
+
+    #[test]
+    fn table_keeps_the_top_five_in_order() {
+        let mut table = HighScores::new(None);
+        for score in [30, 10, 50, 20, 40].iter() {
+            assert!(table.add(entry("a", *score)).is_some());
+        }
+        assert_eq!(scores_of(&table), vec![50, 40, 30, 20, 10]);
+        assert_eq!(table.add(entry("b", 35)), Some(2));
+        assert_eq!(scores_of(&table), vec![50, 40, 35, 30, 20]);
+        assert_eq!(table.add(entry("c", 20)), None);
+        assert_eq!(table.get_entries().len(), HIGH_SCORE_COUNT);
+    }
+
+    #[test]
+    fn loading_skips_corrupted_lines() {
+        let path = env::temp_dir().join(format!("snake-scores-{}.txt", process::id()));
+        let mut table = HighScores::new(Some(path.clone()));
+        table.add(entry("Ada", 40));
+        table.add(entry("Bo", 20));
+        table.save().unwrap();
+        let mut text = fs::read_to_string(&path).unwrap();
+        text.push_str("lots\tCy\t2024-03-01\t35\t25\t0.3\n");
+        text.push_str("30\tDee\n\n");
+        text.push_str("30\tEd\t2024-03-02\t35\t25\t0.3\n");
+        fs::write(&path, text).unwrap();
+        let loaded = HighScores::load(&path);
+        let names: Vec<&str> = loaded.get_entries().iter()
+                .map(|entry| entry.name.as_str())
+                .collect();
+        assert_eq!(names, vec!["Ada", "Ed", "Bo"]);
+
+        fs::write(&path, "not a score table\n40\tAda\t2024-02-29\t35\t25\t0.3\n")
+                .unwrap();
+        assert!(HighScores::load(&path).get_entries().is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn names_are_cleaned() {
+        assert_eq!(clean_name("  Ada\t "), "Ada");
+        assert_eq!(clean_name(""), "Anonymous");
+        assert_eq!(clean_name("Bartholomew the Great"), "Bartholomew");
+    }
+
+    #[test]
+    fn dates_are_formatted_from_the_epoch() {
+        assert_eq!(date_of(0), "1970-01-01");
+        assert_eq!(date_of(86_399), "1970-01-01");
+        assert_eq!(date_of(951_782_400), "2000-02-29");
+        assert_eq!(date_of(1_709_164_800), "2024-02-29");
+        assert_eq!(date_of(1_735_689_599), "2024-12-31");
+        assert_eq!(today().len(), "YYYY-MM-DD".len());
+    }
+
+}
@@ -0,0 +1,385 @@
+use serde::{Serialize, Deserialize};
+use crate::game::Game;
+use crate::grid::Direction;
+
+
+const PLAYER_KEYS: [[&str; 4]; 4] = [
+    ["Up", "Down", "Left", "Right"],
+    ["W", "S", "A", "D"],
+    ["I", "K", "J", "L"],
+    ["NumPad8", "NumPad5", "NumPad4", "NumPad6"],
+];
+const PAUSE_BUTTONS: [&str; 2] = ["Space", "P"];
+const RESTART_BUTTONS: [&str; 1] = ["Return"];
+const QUIT_BUTTONS: [&str; 1] = ["Escape"];
+const STEP_BUTTONS: [&str; 1] = ["N"];
+const THEME_BUTTONS: [&str; 1] = ["T"];
+
+
+/// What a button asks the game for, once looked up in the controls.
+#[derive(Clone, PartialEq)]
+pub enum Action {
+    /// Steers the snake of a player
+    Turn(usize, Direction),
+    /// Turns the snake of a player a quarter to its left, whichever way
+    /// it goes
+    TurnLeft(usize),
+    TurnRight(usize),
+    Pause,
+    Restart,
+    Quit,
+    /// Moves a paused game on by a single step
+    Step,
+    NextTheme,
+}
+
+/// How a player's buttons steer their snake.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scheme {
+    /// A button for each direction
+    Absolute,
+    /// The left and right buttons turn the snake from the way it goes,
+    /// the up and down ones do nothing
+    Relative,
+}
+
+/// Which buttons do what, by the names the window gives them: key names
+/// such as `Up`, `W`, `Space` or `NumPad8`, `Pad0-Button3` for the fourth
+/// button of the first gamepad and `Pad0-Hat0-Up` for its d-pad.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Controls {
+    pub pause: Vec<String>,
+    pub restart: Vec<String>,
+    /// Leaves the round for the main menu
+    pub quit: Vec<String>,
+    pub step: Vec<String>,
+    /// Switches to the next theme
+    pub theme: Vec<String>,
+    /// One per player, from the first
+    pub players: Vec<PlayerControls>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlayerControls {
+    pub scheme: Scheme,
+    pub up: Vec<String>,
+    pub down: Vec<String>,
+    pub left: Vec<String>,
+    pub right: Vec<String>,
+}
+
+/// A list of buttons in the controls, as the settings rebind it.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Binding {
+    Up(usize),
+    Down(usize),
+    Left(usize),
+    Right(usize),
+    Pause,
+    Restart,
+    Quit,
+    Step,
+    Theme,
+}
+
+
+impl Default for Controls {
+
+    fn default() -> Controls {
+        Controls {
+            pause: to_names(&PAUSE_BUTTONS),
+            restart: to_names(&RESTART_BUTTONS),
+            quit: to_names(&QUIT_BUTTONS),
+            step: to_names(&STEP_BUTTONS),
+            theme: to_names(&THEME_BUTTONS),
+            players: (0..PLAYER_KEYS.len()).map(PlayerControls::of_player).collect(),
+        }
+    }
+
+}
+
+
+impl Default for PlayerControls {
+
+    fn default() -> PlayerControls {
+        PlayerControls {
+            scheme: Scheme::Absolute,
+            up: Vec::new(),
+            down: Vec::new(),
+            left: Vec::new(),
+            right: Vec::new(),
+        }
+    }
+
+}
+
+
+impl Action {
+
+    /// The same action for the snake of `player`, e.g. for a networked
+    /// game where every player steers their own snake with the first
+    /// player's buttons.
+    pub fn with_player(&self, player: usize) -> Action {
+        match self {
+            Action::Turn(_, direction) => Action::Turn(player, direction.clone()),
+            Action::TurnLeft(_) => Action::TurnLeft(player),
+            Action::TurnRight(_) => Action::TurnRight(player),
+            other => other.clone(),
+        }
+    }
+
+    /// The player and the direction their snake is to take, for a turn
+    /// of a snake that is in `game`.
+    pub fn get_turn(&self, game: &Game) -> Option<(usize, Direction)> {
+        let heading = |player: usize| game.get_snakes().get(player)
+                .map(|snake| snake.get_heading().clone());
+        match self {
+            Action::Turn(player, direction) => Some((*player, direction.clone()))
+                    .filter(|(player, _)| *player < game.get_player_count()),
+            Action::TurnLeft(player) => heading(*player)
+                    .map(|direction| (*player, direction.turned_left())),
+            Action::TurnRight(player) => heading(*player)
+                    .map(|direction| (*player, direction.turned_right())),
+            Action::Pause | Action::Restart | Action::Quit
+                    | Action::Step | Action::NextTheme => None,
+        }
+    }
+
+}
+
+
+impl Controls {
+
+    /// What the button named `button` does, player buttons first. Names
+    /// are matched regardless of case.
+    pub fn get_action(&self, button: &str) -> Option<Action> {
+        let bound = |buttons: &[String]| {
+            buttons.iter().any(|name| name.eq_ignore_ascii_case(button))
+        };
+        for (player, controls) in self.players.iter().enumerate() {
+            let direction = if bound(&controls.up) {
+                Direction::Up
+            } else if bound(&controls.down) {
+                Direction::Down
+            } else if bound(&controls.left) {
+                Direction::Left
+            } else if bound(&controls.right) {
+                Direction::Right
+            } else {
+                continue;
+            };
+            let action = match (controls.scheme, direction) {
+                (Scheme::Absolute, direction) => Action::Turn(player, direction),
+                (Scheme::Relative, Direction::Left) => Action::TurnLeft(player),
+                (Scheme::Relative, Direction::Right) => Action::TurnRight(player),
+                (Scheme::Relative, _) => continue,
+            };
+            return Some(action);
+        }
+        if bound(&self.pause) {
+            Some(Action::Pause)
+        } else if bound(&self.restart) {
+            Some(Action::Restart)
+        } else if bound(&self.quit) {
+            Some(Action::Quit)
+        } else if bound(&self.step) {
+            Some(Action::Step)
+        } else if bound(&self.theme) {
+            Some(Action::NextTheme)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_buttons(&self, binding: Binding) -> &[String] {
+        let buttons = match binding {
+            Binding::Pause => &self.pause,
+            Binding::Restart => &self.restart,
+            Binding::Quit => &self.quit,
+            Binding::Step => &self.step,
+            Binding::Theme => &self.theme,
+            Binding::Up(player) | Binding::Down(player)
+                    | Binding::Left(player) | Binding::Right(player) => {
+                match self.players.get(player) {
+                    Some(controls) => controls.get_buttons(binding),
+                    None => return &[],
+                }
+            },
+        };
+        buttons
+    }
+
+    /// Makes `button` the only one of `binding`, taking it off whatever
+    /// else it did so a button never does two things.
+    pub fn bind(&mut self, binding: Binding, button: &str) {
+        let others = self.players.iter_mut()
+                .flat_map(|controls| vec![&mut controls.up, &mut controls.down,
+                        &mut controls.left, &mut controls.right])
+                .chain(vec![&mut self.pause, &mut self.restart, &mut self.quit,
+                        &mut self.step, &mut self.theme]);
+        for buttons in others {
+            buttons.retain(|name| !name.eq_ignore_ascii_case(button));
+        }
+        if let Binding::Up(player) | Binding::Down(player)
+                | Binding::Left(player) | Binding::Right(player) = binding {
+            while self.players.len() <= player {
+                self.players.push(PlayerControls::default());
+            }
+        }
+        let buttons = match binding {
+            Binding::Pause => &mut self.pause,
+            Binding::Restart => &mut self.restart,
+            Binding::Quit => &mut self.quit,
+            Binding::Step => &mut self.step,
+            Binding::Theme => &mut self.theme,
+            Binding::Up(player) => &mut self.players[player].up,
+            Binding::Down(player) => &mut self.players[player].down,
+            Binding::Left(player) => &mut self.players[player].left,
+            Binding::Right(player) => &mut self.players[player].right,
+        };
+        *buttons = vec![String::from(button)];
+    }
+
+    /// The scheme of `player`, absolute when they have no controls.
+    pub fn get_scheme(&self, player: usize) -> Scheme {
+        self.players.get(player).map_or(Scheme::Absolute, |controls| controls.scheme)
+    }
+
+    pub fn set_scheme(&mut self, player: usize, scheme: Scheme) {
+        while self.players.len() <= player {
+            self.players.push(PlayerControls::default());
+        }
+        self.players[player].scheme = scheme;
+    }
+
+}
+
+
+impl PlayerControls {
+
+    /// The keyboard keys the game gives `player`, and the d-pad of the
+    /// gamepad of the same number.
+    pub fn of_player(player: usize) -> PlayerControls {
+        let keys = PLAYER_KEYS.get(player).cloned().unwrap_or_default();
+        let pad = |direction: &str| format!("Pad{}-Hat0-{}", player, direction);
+        let buttons = |index: usize, direction: &str| {
+            keys.get(index).map(|key| String::from(*key)).into_iter()
+                    .chain(Some(pad(direction)))
+                    .collect()
+        };
+        PlayerControls {
+            scheme: Scheme::Absolute,
+            up: buttons(0, "Up"),
+            down: buttons(1, "Down"),
+            left: buttons(2, "Left"),
+            right: buttons(3, "Right"),
+        }
+    }
+
+    fn get_buttons(&self, binding: Binding) -> &Vec<String> {
+        match binding {
+            Binding::Up(_) => &self.up,
+            Binding::Down(_) => &self.down,
+            Binding::Left(_) => &self.left,
+            _ => &self.right,
+        }
+    }
+
+}
+
+
+impl Binding {
+
+    /// The entry of the controls menu, with the buttons bound in
+    /// `controls`.
+    pub fn get_label(&self, controls: &Controls) -> String {
+        let name = match self {
+            Binding::Up(_) => "Up",
+            Binding::Down(_) => "Down",
+            Binding::Left(_) => "Left",
+            Binding::Right(_) => "Right",
+            Binding::Pause => "Pause",
+            Binding::Restart => "Restart",
+            Binding::Quit => "Quit",
+            Binding::Step => "Step",
+            Binding::Theme => "Theme",
+        };
+        let buttons = controls.get_buttons(*self);
+        if buttons.is_empty() {
+            format!("{}: none", name)
+        } else {
+            format!("{}: {}", name, buttons.join(", "))
+        }
+    }
+
+}
+
+
+
+fn to_names(buttons: &[&str]) -> Vec<String> {
+    buttons.iter().map(|button| String::from(*button)).collect()
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn absolute_buttons_steer_their_players_snake() {
+        let controls = Controls::default();
+        assert!(controls.get_action("Up") == Some(Action::Turn(0, Direction::Up)));
+        assert!(controls.get_action("a") == Some(Action::Turn(1, Direction::Left)));
+        assert!(controls.get_action("Pad2-Hat0-Down")
+                == Some(Action::Turn(2, Direction::Down)));
+        assert!(controls.get_action("space") == Some(Action::Pause));
+        assert!(controls.get_action("Return") == Some(Action::Restart));
+        assert!(controls.get_action("F12").is_none());
+    }
+
+    #[test]
+    fn relative_buttons_turn_from_the_heading() {
+        let mut controls = Controls::default();
+        controls.set_scheme(1, Scheme::Relative);
+        assert!(controls.get_action("A") == Some(Action::TurnLeft(1)));
+        assert!(controls.get_action("D") == Some(Action::TurnRight(1)));
+        assert!(controls.get_action("W").is_none());
+        assert!(controls.get_action("S").is_none());
+        assert!(controls.get_action("Left") == Some(Action::Turn(0, Direction::Left)));
+        assert!(controls.get_scheme(1) == Scheme::Relative);
+        assert!(controls.get_scheme(9) == Scheme::Absolute);
+    }
+
+    #[test]
+    fn bind_takes_the_button_off_everything_else() {
+        let mut controls = Controls::default();
+        controls.bind(Binding::Pause, "w");
+        assert_eq!(controls.pause, vec![String::from("w")]);
+        assert!(controls.players[1].up == vec![String::from("Pad1-Hat0-Up")]);
+        assert!(controls.get_action("W") == Some(Action::Pause));
+
+        assert!(controls.get_action("Space").is_none());
+
+        controls.bind(Binding::Left(0), "W");
+        assert!(controls.pause.is_empty());
+        assert!(controls.players[0].left == vec![String::from("W")]);
+        assert!(controls.get_action("w") == Some(Action::Turn(0, Direction::Left)));
+    }
+
+    #[test]
+    fn bind_adds_controls_for_new_players() {
+        let mut controls = Controls::default();
+        controls.bind(Binding::Right(5), "X");
+        assert_eq!(controls.players.len(), 6);
+        assert!(controls.players[4].right.is_empty());
+        assert!(controls.get_action("x") == Some(Action::Turn(5, Direction::Right)));
+        assert_eq!(Binding::Right(5).get_label(&controls), "Right: X");
+        assert_eq!(Binding::Up(5).get_label(&controls), "Up: none");
+    }
+
+}
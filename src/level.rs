@@ -0,0 +1,258 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::config::Config;
+use crate::grid::{Position, Direction};
+
+
+const BUILTIN_LEVELS: [(&str, &str); 4] = [
+    ("pillars", include_str!("../levels/pillars.txt")),
+    ("cross", include_str!("../levels/cross.txt")),
+    ("rooms", include_str!("../levels/rooms.txt")),
+    ("tunnel", include_str!("../levels/tunnel.txt")),
+];
+
+
+/// A board layout read from an ASCII grid, one character per block:
+///
+/// * `#` a wall
+/// * `.` or a space, an empty block
+/// * `*` an empty block where food may spawn; when a level has none, food
+///   spawns anywhere
+/// * `^`, `v`, `<` or `>` the snake's head, pointing where it starts moving
+///
+/// Every row is as wide as the others. Lines starting with `;` are
+/// comments and a `name: ...` line names the level.
+pub struct Level {
+    name: String,
+    width: u32,
+    height: u32,
+    walls: Vec<Position>,
+    food_zone: Vec<Position>,
+    spawn: Position,
+    direction: Direction,
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(io::Error),
+    Format(String),
+}
+
+
+impl Level {
+
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTIN_LEVELS.iter().map(|(name, _)| *name)
+    }
+
+    /// Finds a built-in level by name, or else loads `name` as a file.
+    pub fn find(name: &str) -> Result<Level, LevelError> {
+        match BUILTIN_LEVELS.iter().find(|(builtin, _)| *builtin == name) {
+            Some((_, text)) => Level::parse(text),
+            None => Level::load(name),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Level, LevelError> {
+        let text = fs::read_to_string(path)?;
+        Level::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Level, LevelError> {
+        let mut name = String::from("Untitled");
+        let mut rows = Vec::new();
+        for line in text.lines() {
+            if line.starts_with(';') {
+                continue;
+            } else if let Some(value) = line.strip_prefix("name:") {
+                name = String::from(value.trim());
+            } else if !line.trim().is_empty() {
+                rows.push(line.trim_end());
+            }
+        }
+
+        let width = rows.iter().map(|row| row.chars().count()).max()
+                .ok_or_else(|| format_error("the level has no rows"))?;
+        if let Some(row) = rows.iter().position(|row| row.chars().count() != width) {
+            return Err(format_error(&format!(
+                    "row {} is {} blocks wide, not {}",
+                    row + 1, rows[row].chars().count(), width)));
+        }
+        let mut walls = Vec::new();
+        let mut food_zone = Vec::new();
+        let mut spawn = None;
+        for (row, line) in rows.iter().enumerate() {
+            for (column, c) in line.chars().enumerate() {
+                let position = Position::new(column as u32, row as u32);
+                let direction = match c {
+                    '#' => {
+                        walls.push(position);
+                        continue;
+                    },
+                    '*' => {
+                        food_zone.push(position);
+                        continue;
+                    },
+                    '.' | ' ' => continue,
+                    '^' => Direction::Up,
+                    'v' => Direction::Down,
+                    '<' => Direction::Left,
+                    '>' => Direction::Right,
+                    _ => return Err(format_error(&format!(
+                            "unknown block '{}' at row {}, column {}",
+                            c, row + 1, column + 1))),
+                };
+                if spawn.is_some() {
+                    return Err(format_error("the level has more than one snake"));
+                }
+                spawn = Some((position, direction));
+            }
+        }
+        let (spawn, direction) = spawn
+                .ok_or_else(|| format_error("the level has no snake"))?;
+
+        Ok(Level {
+            name,
+            width: width as u32,
+            height: rows.len() as u32,
+            walls,
+            food_zone,
+            spawn,
+            direction,
+        })
+    }
+
+    /// Lays the level out on the playground and snakes of `config`. The
+    /// level draws its own walls, so the uniform border is dropped, and
+    /// rivals start mirrored from the level's snake; `Config::validate`
+    /// refuses a level they do not fit on.
+    pub fn apply(&self, config: &mut Config) {
+        let playground = &mut config.playground;
+        playground.width = self.width;
+        playground.height = self.height;
+        playground.border_width = 0;
+        playground.walls = self.walls.clone();
+        playground.food_zone = self.food_zone.clone();
+        config.snake.init_position = self.spawn.clone();
+        config.snake.init_direction = self.direction.clone();
+        for (index, rival) in config.rivals.iter_mut().enumerate() {
+            let mirrored = config.snake.mirrored(index, &config.playground);
+            rival.init_position = mirrored.init_position;
+            rival.init_direction = mirrored.init_direction;
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+}
+
+
+impl fmt::Display for LevelError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelError::Io(error) => write!(f, "{}", error),
+            LevelError::Format(message) => write!(f, "{}", message),
+        }
+    }
+
+}
+
+
+impl std::error::Error for LevelError {}
+
+
+impl From<io::Error> for LevelError {
+
+    fn from(error: io::Error) -> LevelError {
+        LevelError::Io(error)
+    }
+
+}
+
+
+
+fn format_error(message: &str) -> LevelError {
+    LevelError::Format(String::from(message))
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn refusal(text: &str) -> String {
+        match Level::parse(text) {
+            Err(LevelError::Format(message)) => message,
+            Err(error) => panic!("refused for the wrong reason: {}", error),
+            Ok(_) => panic!("the level was accepted"),
+        }
+    }
+
+    #[test]
+    fn parse_reads_blocks_and_name() {
+        let level = Level::parse("; a comment\nname: Tiny\n#####\n#*.<#\n#####\n")
+                .unwrap();
+        assert_eq!(level.get_name(), "Tiny");
+        assert_eq!((level.width, level.height), (5, 3));
+        assert_eq!(level.walls.len(), 12);
+        assert!(level.food_zone == vec![Position (1, 1)]);
+        assert!(level.spawn == Position (3, 1));
+        assert!(level.direction == Direction::Left);
+    }
+
+    #[test]
+    fn parse_refuses_malformed_levels() {
+        assert_eq!(refusal("; only comments\n"), "the level has no rows");
+        assert_eq!(refusal("#####\n#.?>#\n#####\n"),
+                "unknown block '?' at row 2, column 3");
+        assert_eq!(refusal("#####\n#..>#\n####\n"), "row 3 is 4 blocks wide, not 5");
+        assert_eq!(refusal("#####\n#...#\n#####\n"), "the level has no snake");
+        assert_eq!(refusal("#####\n#>.<#\n#####\n"), "the level has more than one snake");
+    }
+
+    #[test]
+    fn a_snake_that_does_not_fit_its_board_is_refused() {
+        // the tail would start inside the left wall
+        let level = Level::parse("######\n#>...#\n######\n").unwrap();
+        let mut config = Config::default();
+        level.apply(&mut config);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn builtin_levels_fit_every_player_count() {
+        for name in Level::builtin_names() {
+            for count in 1..=usize::from(crate::config::MAX_PLAYERS) {
+                let mut config = Config::default();
+                config.set_player_count(count);
+                Level::find(name).unwrap().apply(&mut config);
+                assert!(config.validate().is_ok(), "{} with {} snakes", name, count);
+            }
+        }
+    }
+
+    #[test]
+    fn rivals_are_moved_onto_the_level() {
+        let mut config = Config::default();
+        config.set_player_count(2);
+        let level = Level::parse("#########\n#.......#\n#...>...#\n#.......#\n#########\n")
+                .unwrap();
+        level.apply(&mut config);
+        assert!(config.rivals[0].init_position == Position (4, 2));
+        assert!(config.validate().is_err());
+        let level = Level::parse("#########\n#..>....#\n#.......#\n#.......#\n#########\n")
+                .unwrap();
+        level.apply(&mut config);
+        assert!(config.rivals[0].init_position == Position (5, 3));
+        assert!(config.rivals[0].init_direction == Direction::Left);
+        assert!(config.validate().is_ok());
+    }
+
+}
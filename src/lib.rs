@@ -1,5 +1,23 @@
+pub mod config;
+pub mod grid;
+pub mod level;
+pub mod theme;
+pub mod menu;
+pub mod input;
+pub mod campaign;
 pub mod game;
 pub mod playground;
 pub mod snake;
 pub mod food;
-mod draw;
\ No newline at end of file
+pub mod controller;
+pub mod bench;
+pub mod env;
+pub mod cli;
+pub mod replay;
+pub mod highscores;
+pub mod net;
+pub mod server;
+#[cfg(feature = "terminal")]
+pub mod terminal;
+#[cfg(feature = "window")]
+pub mod draw;
@@ -1,33 +1,916 @@
+use std::env;
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::path::PathBuf;
+use clap::Parser;
 use piston_window::*;
 use piston_window::types::Color;
-use snake::game::Game;
+use snake::campaign::Campaign;
+use snake::cli::{fail, parse_positive, GameArgs, Mode};
+use snake::config::{Config, Overrides, OverrideError, MAX_PLAYERS};
+use snake::game::{Game, Status};
+use snake::replay::{Replay, Playback};
+use snake::highscores::{HighScores, HighScore, is_name_full};
+use snake::net::{Connection, ClientMessage, NetError, ServerMessage};
+use snake::theme::Theme;
+use snake::menu::{Menu, Setting, SETTINGS};
+use snake::input::{Action, Binding, Controls, Scheme};
+use snake::draw::{Assets, Viewport, draw_high_scores, draw_stage_cleared, draw_lobby};
 
 
-const WHITE_COLOR: Color = [1.0, 1.0, 1.0, 1.0];
-const FROG_COLOR: Color = [0.17, 0.51, 0.08, 1.0];
-const BONUS_COLOR: Color = [0.0, 1.0, 1.0, 1.0];
+// the bars around a board that does not fill the window
+const LETTERBOX_COLOR: Color = [0.0, 0.0, 0.0, 1.0];
+const HEADLESS_TICK_LIMIT: u64 = 100_000;
+const RECONNECT_DELAY: f64 = 1.0;
+const MAIN_MENU: [&str; 4] = ["Play", "High Scores", "Settings", "Quit"];
+const MODES: [(Mode, &str); 3] = [
+    (Mode::Classic, "Classic: walled board"),
+    (Mode::Wrap, "Wrap: no walls"),
+    (Mode::Campaign, "Campaign: board after board"),
+];
+const MENU_HINT: &str = "Arrows: choose  Enter: select  Esc: back";
+const CONTROLS_MENU: [&str; 12] = [
+    "Player", "Scheme", "Up", "Down", "Left", "Right", "Pause", "Restart", "Quit", "Step",
+    "Theme", "Back",
+];
+
+
+/// The classic snake game written in rust.
+#[derive(Parser)]
+#[command(name = "snake", version)]
+struct Args {
+    #[command(flatten)]
+    game: GameArgs,
+    /// Size of the window at start, as a multiple of the default; it can
+    /// be resized freely after
+    #[arg(long, default_value_t = 1.0, value_parser = parse_positive)]
+    scale: f64,
+    /// Start in fullscreen
+    #[arg(long)]
+    fullscreen: bool,
+    /// Look of the game: classic, night, an installed theme pack or a
+    /// theme manifest; T switches themes while playing
+    #[arg(long, value_name = "THEME")]
+    theme: Option<String>,
+    /// Glide the snakes from block to block instead of jumping
+    #[arg(long)]
+    smooth: bool,
+    /// Play back a replay from FILE
+    #[arg(long, value_name = "FILE", conflicts_with = "record")]
+    replay: Option<PathBuf>,
+    /// Join the game server at ADDRESS, e.g. 127.0.0.1:7878
+    #[arg(long, value_name = "ADDRESS", conflicts_with_all = ["replay", "record", "headless"])]
+    connect: Option<String>,
+    /// Name shown to the other players of a networked game
+    #[arg(long)]
+    name: Option<String>,
+    /// Run without a window; with --replay, checks the replay reproduces
+    /// its recorded score
+    #[arg(long)]
+    headless: bool,
+}
+
+/// What the window shows. Scenes stack up: the top one gets the keys, and
+/// going back closes it to show the one under it again.
+enum Scene {
+    MainMenu(Menu),
+    ModeSelect(Menu),
+    Settings(Menu),
+    HighScores(Menu),
+    Controls(ControlsPage),
+    InGame(Box<Round>),
+    /// The end of the round under it, until Enter starts the next one
+    GameOver,
+}
+
+// what a scene asks for after a key press
+enum Transition {
+    Stay,
+    Push(Scene),
+    Pop,
+    /// Back to the main menu
+    Home,
+    Quit,
+}
+
+/// The buttons of one player at a time, and those of the game. Picking a
+/// binding waits for the button to put on it.
+struct ControlsPage {
+    menu: Menu,
+    player: usize,
+    waiting: Option<Binding>,
+}
+
+/// A game being played, with what lasts from one round to the next.
+struct Round {
+    game: Game,
+    config: Config,
+    campaign: Option<Campaign>,
+    replay: Replay,
+    name_entry: Option<String>,
+    over: bool,
+}
+
+/// What the scenes share: the settings as kept in the config file, the
+/// theme shown and the high score table.
+struct Session<'a> {
+    args: &'a Args,
+    settings: Config,
+    theme: Option<String>,
+    scores: HighScores,
+}
+
+
+impl Round {
+
+    fn start(session: &Session, mode: Mode) -> Result<Round, String> {
+        let config = build_config(session.args, session.settings.clone(), mode)
+                .map_err(|e| e.to_string())?;
+        let campaign = mode.load_campaign();
+        let game = Game::from_config(&config, campaign.as_ref(), session.args.game.seed)
+                .map_err(|e| format!("Invalid {}", e))?;
+        Ok(Round {
+            replay: Replay::record(&game).with_campaign(campaign.as_ref()),
+            game,
+            config,
+            campaign,
+            name_entry: None,
+            over: false,
+        })
+    }
+
+    // what `button` does in the round, by the controls it was started with
+    fn get_action(&self, button: &Button) -> Option<Action> {
+        button_name(button).and_then(|name| self.config.controls.get_action(&name))
+    }
+
+    fn handle_button(&mut self, button: Button) -> Transition {
+        match self.get_action(&button) {
+            Some(Action::Pause) => self.game.toggle_pause(),
+            Some(Action::Restart) => self.restart(),
+            Some(Action::Quit) => return Transition::Home,
+            Some(Action::Step) => {
+                if let Status::Paused = self.game.get_status() {
+                    self.game.single_step();
+                }
+            },
+            // the window switches themes
+            Some(Action::NextTheme) | None => (),
+            Some(turn) => {
+                if let Some((player, direction)) = turn.get_turn(&self.game) {
+                    self.replay.record_turn(&self.game, player, direction.clone());
+                    self.game.turn_snake(player, direction);
+                }
+            },
+        }
+        Transition::Stay
+    }
+
+    /// Saves what is kept of a round that just ended: its replay, the
+    /// campaign progress, and asks for a name when the score makes the
+    /// table. Tells whether the round had just ended.
+    fn finish(&mut self, session: &mut Session) -> bool {
+        if self.over || !self.game.is_finished() {
+            return false;
+        }
+        self.over = true;
+        if let Some(path) = &session.args.game.record {
+            self.replay.finish(&self.game);
+            if let Err(e) = self.replay.save(path) {
+                eprintln!("Failed to save replay to {}: {}", path.display(), e);
+            }
+        }
+        match (self.game.get_status(), self.campaign.as_mut()) {
+            // the table ranks single player games only
+            (Status::GameOver, _) if self.game.get_player_count() == 1
+                    && self.game.get_controller(0).is_none()
+                    && session.scores.qualifies(self.game.get_score()) => {
+                self.name_entry = Some(String::new());
+            },
+            (Status::Cleared, Some(campaign)) => {
+                campaign.advance();
+                if let Err(e) = campaign.save() {
+                    eprintln!("Failed to save campaign progress: {}", e);
+                }
+            },
+            _ => (),
+        }
+        true
+    }
+
+    fn enter_text(&mut self, text: &str) {
+        if let Some(name) = self.name_entry.as_mut() {
+            for c in text.chars().filter(|c| !c.is_control()) {
+                if !is_name_full(name) {
+                    name.push(c);
+                }
+            }
+        }
+    }
+
+    // the buttons of the game over scene: the name of a new record, then
+    // Enter or the restart button for the next round
+    fn handle_end_button(&mut self, button: Button, session: &mut Session)
+            -> Transition {
+        if let Some(name) = self.name_entry.as_mut() {
+            match button {
+                Button::Keyboard(Key::Backspace) => {
+                    name.pop();
+                },
+                Button::Keyboard(Key::Return) => {
+                    session.scores.add(HighScore::from_game(name, &self.game));
+                    if let Err(e) = session.scores.save() {
+                        eprintln!("Failed to save high scores: {}", e);
+                    }
+                    self.name_entry = None;
+                },
+                Button::Keyboard(Key::Escape) => return Transition::Home,
+                _ => (),
+            }
+            return Transition::Stay;
+        }
+        match (button, self.get_action(&button)) {
+            (Button::Keyboard(Key::Return), _) | (_, Some(Action::Restart)) => {
+                match self.next() {
+                    Ok(()) => Transition::Pop,
+                    Err(message) => {
+                        eprintln!("{}", message);
+                        Transition::Home
+                    },
+                }
+            },
+            (Button::Keyboard(Key::Escape), _) | (_, Some(Action::Quit)) => {
+                Transition::Home
+            },
+            _ => Transition::Stay,
+        }
+    }
+
+    // the next campaign stage once this one is cleared, the first one once
+    // the campaign is complete, or else the same board again
+    fn next(&mut self) -> Result<(), String> {
+        match (self.game.get_status(), self.campaign.as_mut()) {
+            (Status::Cleared, Some(campaign)) => {
+                if campaign.is_complete() {
+                    campaign.start_over();
+                }
+                let campaign = &*campaign;
+                self.game = Game::from_config(&self.config, Some(campaign), None)
+                        .map_err(|e| format!("Invalid {}", e))?;
+                self.replay = Replay::record(&self.game).with_campaign(Some(campaign));
+                self.over = false;
+            },
+            _ => self.restart(),
+        }
+        Ok(())
+    }
+
+    fn restart(&mut self) {
+        self.game.restart();
+        self.replay = Replay::record(&self.game)
+                .with_campaign(self.campaign.as_ref());
+        self.over = false;
+    }
+
+    // the game, with the high scores or the next stage over it once
+    // `ended`
+    fn draw(&self, ended: bool, scores: &HighScores, assets: &mut Assets,
+            context: &Context, graphics: &mut G2d) {
+        let playground = self.game.get_playground();
+        let viewport = Viewport::of(playground, context.get_view_size());
+        self.game.draw(self.config.smooth, assets, &viewport, context, graphics);
+        if !ended {
+            return;
+        }
+        match (self.game.get_status(), self.campaign.as_ref()) {
+            (Status::GameOver, _) => {
+                draw_high_scores(scores, self.name_entry.as_deref(), playground,
+                        assets, &viewport, context, graphics);
+            },
+            (Status::Cleared, Some(campaign)) => {
+                draw_stage_cleared(campaign, playground, assets, &viewport,
+                        context, graphics);
+            },
+            _ => (),
+        }
+    }
+
+}
+
+
+impl ControlsPage {
+
+    fn new(controls: &Controls) -> ControlsPage {
+        let entries = CONTROLS_MENU.iter().map(|entry| String::from(*entry)).collect();
+        let mut page = ControlsPage {
+            menu: Menu::new("Controls", entries)
+                    .with_hint("Enter: rebind  Left, right: change  Esc: back"),
+            player: 0,
+            waiting: None,
+        };
+        page.refresh(controls);
+        page
+    }
+
+    // the binding of the entry at `index`, for the player shown
+    fn get_binding(&self, index: usize) -> Option<Binding> {
+        match index {
+            2 => Some(Binding::Up(self.player)),
+            3 => Some(Binding::Down(self.player)),
+            4 => Some(Binding::Left(self.player)),
+            5 => Some(Binding::Right(self.player)),
+            6 => Some(Binding::Pause),
+            7 => Some(Binding::Restart),
+            8 => Some(Binding::Quit),
+            9 => Some(Binding::Step),
+            10 => Some(Binding::Theme),
+            _ => None,
+        }
+    }
+
+    fn refresh(&mut self, controls: &Controls) {
+        self.menu.set_entry(0, format!("Player: {}", self.player + 1));
+        let scheme = match controls.get_scheme(self.player) {
+            Scheme::Absolute => "a key per direction",
+            Scheme::Relative => "left and right turn",
+        };
+        self.menu.set_entry(1, format!("Scheme: {}", scheme));
+        for index in 0..CONTROLS_MENU.len() {
+            if let Some(binding) = self.get_binding(index) {
+                self.menu.set_entry(index, binding.get_label(controls));
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: Key, session: &mut Session) -> Transition {
+        let selected = self.menu.get_selected().unwrap_or(0);
+        let controls = &mut session.settings.controls;
+        match (key, selected) {
+            (Key::Up, _) => self.menu.select_previous(),
+            (Key::Down, _) => self.menu.select_next(),
+            (Key::Left, 0) => {
+                self.player = (self.player + usize::from(MAX_PLAYERS) - 1)
+                        % usize::from(MAX_PLAYERS);
+            },
+            (Key::Right, 0) | (Key::Return, 0) => {
+                self.player = (self.player + 1) % usize::from(MAX_PLAYERS);
+            },
+            (Key::Left, 1) | (Key::Right, 1) | (Key::Return, 1) => {
+                let scheme = match controls.get_scheme(self.player) {
+                    Scheme::Absolute => Scheme::Relative,
+                    Scheme::Relative => Scheme::Absolute,
+                };
+                controls.set_scheme(self.player, scheme);
+                self.menu.set_message(session.save_settings().err());
+            },
+            (Key::Return, index) => match self.get_binding(index) {
+                Some(binding) => {
+                    self.waiting = Some(binding);
+                    self.menu.set_message(Some(String::from(
+                            "Press the button to use, Esc to keep the current one")));
+                },
+                None => return Transition::Pop,
+            },
+            (Key::Escape, _) => return Transition::Pop,
+            _ => (),
+        }
+        self.refresh(&session.settings.controls);
+        Transition::Stay
+    }
+
+    // puts `button` on the binding waiting for one
+    fn bind(&mut self, button: Button, session: &mut Session) {
+        let binding = match self.waiting.take() {
+            Some(binding) => binding,
+            None => return,
+        };
+        self.menu.set_message(None);
+        if let Button::Keyboard(Key::Escape) = button {
+            return;
+        }
+        if let Some(name) = button_name(&button) {
+            session.settings.controls.bind(binding, &name);
+            self.menu.set_message(session.save_settings().err());
+            self.refresh(&session.settings.controls);
+        }
+    }
+
+}
+
+
+impl<'a> Session<'a> {
+
+    // settings changed in the menu go to the config file the game was
+    // started with
+    fn save_settings(&self) -> Result<(), String> {
+        let path = self.args.game.config.clone()
+                .or_else(Config::default_path)
+                .ok_or_else(|| String::from("No folder to save settings in"))?;
+        self.settings.save(&path).map_err(|e| {
+            format!("Failed to save settings to {}: {}", path.display(), e)
+        })
+    }
+
+}
 
 
 fn main() {
-    let mut game = Game::default();
-    let size = game.get_size();
-    let mut window: PistonWindow = 
-        WindowSettings::new("Rusty Snake", size)
-        .exit_on_esc(true).build()
-        .unwrap_or_else(|e| {
-            panic!("Failed to build PistonWindow: {}", e)
+    let args = Args::parse();
+    if let Some(path) = &args.replay {
+        let replay = Replay::load(path).unwrap_or_else(|e| {
+            fail(1, &format!("Failed to load replay {}: {}", path.display(), e))
         });
+        if args.headless {
+            verify(replay);
+        } else {
+            watch(replay, &args);
+        }
+        return;
+    }
+    if let Some(address) = &args.connect {
+        join(address, &args);
+        return;
+    }
+
+    let settings = load_settings(&args);
+    if !args.headless {
+        run(settings, &args);
+        return;
+    }
+    let config = build_config(&args, settings, args.game.mode)
+            .unwrap_or_else(|e| fail(e.get_exit_code(), &e.to_string()));
+    let campaign = args.game.mode.load_campaign();
+    let game = Game::from_config(&config, campaign.as_ref(), args.game.seed)
+            .unwrap_or_else(|e| fail(1, &format!("Invalid {}", e)));
+    run_headless(game);
+}
+
+// the config file as it is, before the command line changes anything
+fn load_settings(args: &Args) -> Config {
+    Config::load_or_default(args.game.config.as_deref())
+            .unwrap_or_else(|e| fail(e.get_exit_code(), &e.to_string()))
+}
+
+/// The settings a round of `mode` is played with: `base` with the command
+/// line applied.
+fn build_config(args: &Args, mut config: Config, mode: Mode)
+        -> Result<Config, OverrideError> {
+    // nobody steers a player's snake without a window, so the bots get
+    // every seat there
+    let players = match (args.game.players, args.headless && !args.game.bots.is_empty()) {
+        (Some(players), true) if players > 0 => {
+            return Err(OverrideError::Setting(String::from(
+                    "Bots play alone with --headless, --players must be 0")));
+        },
+        (None, true) => Some(0),
+        (players, _) => players.map(usize::from),
+    };
+    let overrides = Overrides {
+        theme: args.theme.clone(),
+        smooth: args.smooth,
+        players,
+        ..args.game.get_overrides(mode)
+    };
+    config.apply_overrides(&overrides)?;
+    Ok(config)
+}
+
+/// Shows the main menu and everything reached from it, until the player
+/// quits.
+fn run(settings: Config, args: &Args) {
+    let mut session = Session {
+        args,
+        theme: args.theme.clone().or_else(|| settings.theme.clone()),
+        settings,
+        scores: HighScores::default(),
+    };
+    let size = session.settings.playground.build().get_size();
+    let mut window = build_window(size, args, false);
+    let mut assets = load_assets(&mut window, session.theme.as_deref());
+    let mut theme = session.theme.clone();
+    let mut stack = vec![Scene::MainMenu(main_menu())];
+    while let Some(event) = window.next() {
+        if let Some(text) = event.text_args() {
+            if let [.., Scene::InGame(round), Scene::GameOver] = stack.as_mut_slice() {
+                round.enter_text(&text);
+            }
+        }
+        if let Some(button) = event.press_args() {
+            let action = match stack.last() {
+                Some(Scene::InGame(round)) => round.get_action(&button),
+                _ => None,
+            };
+            let transition = match action {
+                Some(Action::NextTheme) => {
+                    next_theme(&mut window, &mut assets);
+                    Transition::Stay
+                },
+                _ => handle_button(&mut stack, button, &mut session),
+            };
+            match transition {
+                Transition::Stay => (),
+                Transition::Push(scene) => stack.push(scene),
+                Transition::Pop => {
+                    stack.pop();
+                },
+                Transition::Home => stack.truncate(1),
+                Transition::Quit => stack.clear(),
+            }
+        }
+        if stack.is_empty() {
+            break;
+        }
+        if session.theme != theme {
+            theme = session.theme.clone();
+            let name = theme.as_deref().unwrap_or("classic");
+            let result = Theme::find(name).map_err(|e| e.to_string())
+                    .and_then(|found| assets.switch(&mut window.factory, found)
+                            .map_err(|e| e.to_string()));
+            if let Err(e) = result {
+                eprintln!("Failed to load theme {}: {}", name, e);
+            }
+        }
+        if let (Some(false), Some(Scene::InGame(round))) =
+                (event.focus_args(), stack.last_mut()) {
+            round.game.pause();
+        }
+        window.draw_2d(&event, |context, graphics| {
+            clear(LETTERBOX_COLOR, graphics);
+            let ended = matches!(stack.last(), Some(Scene::GameOver));
+            let shown = stack.iter().rev()
+                    .find(|scene| !matches!(scene, Scene::GameOver));
+            match shown {
+                Some(Scene::InGame(round)) => round.draw(ended, &session.scores,
+                        &mut assets, &context, graphics),
+                Some(Scene::MainMenu(menu)) | Some(Scene::ModeSelect(menu))
+                        | Some(Scene::Settings(menu)) | Some(Scene::HighScores(menu))
+                        | Some(Scene::Controls(ControlsPage { menu, .. })) => {
+                    let board = session.settings.playground.build();
+                    let viewport = Viewport::of(&board, context.get_view_size());
+                    menu.draw(&board, &mut assets, &viewport, &context, graphics);
+                },
+                Some(Scene::GameOver) | None => (),
+            }
+        });
+        if let Some(Scene::InGame(round)) = stack.last_mut() {
+            event.update(|arg| {
+                round.game.update(arg.dt)
+            });
+            if round.finish(&mut session) {
+                stack.push(Scene::GameOver);
+            }
+        }
+    }
+}
+
+// passes `button` to the scene on top of `stack`; the menus only take
+// keys
+fn handle_button(stack: &mut [Scene], button: Button, session: &mut Session)
+        -> Transition {
+    let (top, rest) = match stack.split_last_mut() {
+        Some(split) => split,
+        None => return Transition::Quit,
+    };
+    match top {
+        Scene::Controls(page) if page.waiting.is_some() => {
+            page.bind(button, session);
+            return Transition::Stay;
+        },
+        Scene::InGame(round) => return round.handle_button(button),
+        Scene::GameOver => return match rest.last_mut() {
+            Some(Scene::InGame(round)) => round.handle_end_button(button, session),
+            _ => Transition::Pop,
+        },
+        _ => (),
+    }
+    let key = match button {
+        Button::Keyboard(key) => key,
+        _ => return Transition::Stay,
+    };
+    match top {
+        Scene::MainMenu(menu) | Scene::ModeSelect(menu)
+                | Scene::Settings(menu) if key == Key::Up || key == Key::Down => {
+            if key == Key::Up {
+                menu.select_previous();
+            } else {
+                menu.select_next();
+            }
+            Transition::Stay
+        },
+        Scene::MainMenu(menu) => match (key, menu.get_selected()) {
+            (Key::Return, Some(0)) => {
+                Transition::Push(Scene::ModeSelect(mode_menu(session.args.game.mode)))
+            },
+            (Key::Return, Some(1)) => {
+                Transition::Push(Scene::HighScores(high_score_menu(&session.scores)))
+            },
+            (Key::Return, Some(2)) => {
+                Transition::Push(Scene::Settings(settings_menu(&session.settings)))
+            },
+            (Key::Return, _) | (Key::Escape, _) => Transition::Quit,
+            _ => Transition::Stay,
+        },
+        Scene::ModeSelect(menu) => match (key, menu.get_selected()) {
+            (Key::Return, Some(index)) => match Round::start(session, MODES[index].0) {
+                Ok(round) => Transition::Push(Scene::InGame(Box::new(round))),
+                Err(message) => {
+                    menu.set_message(Some(message));
+                    Transition::Stay
+                },
+            },
+            (Key::Escape, _) => Transition::Pop,
+            _ => Transition::Stay,
+        },
+        Scene::Settings(menu) => change_setting(menu, key, session),
+        Scene::HighScores(_) => match key {
+            Key::Return | Key::Escape => Transition::Pop,
+            _ => Transition::Stay,
+        },
+        Scene::Controls(page) => page.handle_key(key, session),
+        Scene::InGame(_) | Scene::GameOver => Transition::Stay,
+    }
+}
+
+fn main_menu() -> Menu {
+    let entries = MAIN_MENU.iter().map(|entry| String::from(*entry)).collect();
+    Menu::new("Rusty Snake", entries)
+            .with_hint("Arrows: choose  Enter: select  Esc: quit")
+}
+
+fn mode_menu(mode: Mode) -> Menu {
+    let entries = MODES.iter().map(|(_, label)| String::from(*label)).collect();
+    let selected = MODES.iter().position(|(entry, _)| *entry == mode).unwrap_or(0);
+    Menu::new("Mode", entries).with_selected(selected).with_hint(MENU_HINT)
+}
+
+fn settings_menu(settings: &Config) -> Menu {
+    let mut entries: Vec<String> = SETTINGS.iter()
+            .map(|setting| setting.get_label(settings))
+            .collect();
+    entries.push(String::from("Controls"));
+    entries.push(String::from("Back"));
+    Menu::new("Settings", entries)
+            .with_hint("Left, right: change  Esc: back")
+}
+
+fn high_score_menu(scores: &HighScores) -> Menu {
+    let mut entries: Vec<String> = scores.get_entries().iter().enumerate()
+            .map(|(rank, entry)| format!("{}. {:<12} {:>5}  {}", rank + 1,
+                    entry.name, entry.score, entry.date))
+            .collect();
+    if entries.is_empty() {
+        entries.push(String::from("No scores yet"));
+    }
+    Menu::new("High Scores", entries).without_cursor().with_hint("Esc: back")
+}
+
+// steps the selected setting with the left and right keys, saving the
+// config file after each change
+fn change_setting(menu: &mut Menu, key: Key, session: &mut Session) -> Transition {
+    let selected = menu.get_selected().unwrap_or(0);
+    // the settings are followed by the controls page, then a way back
+    let setting = match (key, SETTINGS.get(selected)) {
+        (Key::Return, None) if selected == SETTINGS.len() => {
+            let page = ControlsPage::new(&session.settings.controls);
+            return Transition::Push(Scene::Controls(page));
+        },
+        (Key::Escape, _) | (Key::Return, None) => return Transition::Pop,
+        (_, None) => return Transition::Stay,
+        (_, Some(setting)) => *setting,
+    };
+    let steps = match key {
+        Key::Left => -1,
+        Key::Right | Key::Return => 1,
+        _ => return Transition::Stay,
+    };
+    setting.adjust(&mut session.settings, steps);
+    if setting == Setting::Theme {
+        session.theme = session.settings.theme.clone();
+    }
+    menu.set_entry(selected, setting.get_label(&session.settings));
+    menu.set_message(session.save_settings().err());
+    Transition::Stay
+}
+
+/// Plays on a server: turns go out as they are pressed, and the window only
+/// renders the states the server sends back.
+fn join(address: &str, args: &Args) {
+    let name = args.name.clone()
+            .or_else(|| env::var("USER").ok())
+            .unwrap_or_else(|| String::from("Player"));
+    let mut connection = Some(Connection::open(address, &name, None)
+            .unwrap_or_else(|e| {
+                fail(1, &format!("Failed to connect to {}: {}", address, e))
+            }));
+    let controls = load_settings(args).controls;
+    let mut token = None;
+    let mut seat = 0;
+    let mut seats = Vec::new();
+    let mut config = Config::default();
+    let mut game: Option<Game> = None;
+    let mut ready = false;
+    let mut reconnect_in = 0.0;
+    // a reconnect under way, kept off the window's thread
+    let mut reconnecting: Option<Receiver<Result<Connection, NetError>>> = None;
+    let lobby_game = config.build_game();
+    let mut window = build_window(lobby_game.get_size(), args, true);
+    let mut assets = load_assets(&mut window, args.theme.as_deref());
+    while let Some(event) = window.next() {
+        let mut lost = false;
+        while let Some(link) = connection.as_ref() {
+            let message = match link.poll() {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                Err(_) => {
+                    lost = true;
+                    break;
+                },
+            };
+            match message {
+                ServerMessage::Welcome { token: seat_token } => token = Some(seat_token),
+                ServerMessage::Refused(reason) => {
+                    fail(1, &format!("The server refused to seat you: {}", reason));
+                },
+                ServerMessage::Lobby(lobby) => seats = lobby,
+                ServerMessage::Config(game_config) => config = *game_config,
+                ServerMessage::Start { player } => {
+                    seat = player;
+                    let new_game = config.build_game();
+                    game = Some(new_game);
+                    ready = false;
+                },
+                ServerMessage::State(snapshot) => {
+                    if let Some(game) = game.as_mut() {
+                        game.restore(&snapshot);
+                    }
+                },
+            }
+        }
+        let in_game = game.as_ref().is_some_and(|game| !game.is_finished());
+        if let (Some(button), Some(link)) = (event.press_args(), connection.as_mut()) {
+            // any player's buttons steer the snake of this seat
+            let action = button_name(&button).and_then(|name| controls.get_action(&name));
+            let turn = action.as_ref().zip(game.as_ref())
+                    .and_then(|(action, game)| action.with_player(seat).get_turn(game));
+            let message = match (button, action, turn) {
+                (_, _, Some((_, direction))) if in_game => {
+                    Some(ClientMessage::Turn(direction))
+                },
+                (Button::Keyboard(Key::Return), _, _) if !in_game => {
+                    ready = !ready;
+                    Some(ClientMessage::Ready(ready))
+                },
+                (_, Some(Action::NextTheme), _) => {
+                    next_theme(&mut window, &mut assets);
+                    None
+                },
+                _ => None,
+            };
+            if let Some(message) = message {
+                lost |= link.send(&message).is_err();
+            }
+        }
+        if lost {
+            connection = None;
+            ready = false;
+            reconnect_in = RECONNECT_DELAY;
+        }
+        if let Some(attempt) = reconnecting.as_ref() {
+            match attempt.try_recv() {
+                Ok(result) => {
+                    connection = result.ok();
+                    reconnecting = None;
+                },
+                Err(TryRecvError::Empty) => (),
+                Err(TryRecvError::Disconnected) => reconnecting = None,
+            }
+        }
+        event.update(|arg| {
+            if connection.is_some() || reconnecting.is_some() {
+                return;
+            }
+            reconnect_in -= arg.dt;
+            if reconnect_in <= 0.0 {
+                reconnect_in = RECONNECT_DELAY;
+                reconnecting = Some(Connection::open_in_background(address,
+                        &name, token));
+            }
+        });
+        window.draw_2d(&event, |context, graphics| {
+            let board = config.playground.build();
+            let playground = game.as_ref().map_or(&board, Game::get_playground);
+            let viewport = Viewport::of(playground, context.get_view_size());
+            clear(LETTERBOX_COLOR, graphics);
+            // the server sends whole steps, with nothing to glide between
+            if let Some(game) = &game {
+                game.draw(false, &mut assets, &viewport, &context, graphics);
+            }
+            let hint = match (connection.is_some(), ready) {
+                (false, _) => "Lost the server, reconnecting...",
+                (true, true) => "Waiting for the other players",
+                (true, false) => "Press Enter when ready",
+            };
+            if !in_game || connection.is_none() {
+                draw_lobby(&seats, hint, game.is_none().then_some(&board),
+                        &mut assets, &viewport, &context, graphics);
+            }
+        });
+    }
+}
+
+fn watch(replay: Replay, args: &Args) {
+    let mut playback = Playback::new(replay);
+    let mut window = build_window(playback.get_game().get_size(), args, true);
+    let mut assets = load_assets(&mut window, args.theme.as_deref());
+    let controls = load_settings(args).controls;
     while let Some(event) = window.next() {
-        let mut factory = window.factory.clone();
-        if let Some(Button::Keyboard(key)) = event.press_args() {
-            game.key_pressed(key);
+        let action = event.press_args().as_ref().and_then(button_name)
+                .and_then(|name| controls.get_action(&name));
+        if let Some(Action::NextTheme) = action {
+            next_theme(&mut window, &mut assets);
         }
         window.draw_2d(&event, |context, graphics| {
-            clear(WHITE_COLOR, graphics);
-            game.draw(&mut factory, &context, graphics);
+            let game = playback.get_game();
+            let viewport = Viewport::of(game.get_playground(),
+                    context.get_view_size());
+            clear(LETTERBOX_COLOR, graphics);
+            game.draw(args.smooth, &mut assets, &viewport, &context, graphics);
         });
         event.update(|arg| {
-            game.update(arg.dt)
+            playback.update(arg.dt)
         });
     }
 }
+
+fn verify(replay: Replay) {
+    let game = replay.play();
+    if let Some(stage) = replay.get_stage() {
+        print!("stage {}, ", stage);
+    }
+    println!("seed {}: score {} after {} ticks (recorded {} after {})",
+            replay.get_seed(), game.get_score(), game.get_ticks(),
+            replay.get_final_score(), replay.get_final_ticks());
+    if !replay.verify() {
+        fail(1, "Replay does not reproduce the recorded result");
+    }
+}
+
+fn run_headless(mut game: Game) {
+    // without walls an unsteered snake would never die
+    while let Status::Running = game.get_status() {
+        if game.get_ticks() >= HEADLESS_TICK_LIMIT {
+            break;
+        }
+        game.tick();
+    }
+    println!("seed {}: score {} after {} ticks",
+            game.get_seed(), game.get_score(), game.get_ticks());
+}
+
+fn window_size(size: [f64; 2], args: &Args) -> [f64; 2] {
+    [size[0] * args.scale, size[1] * args.scale]
+}
+
+// the menus use Esc to go back, so they close the window themselves
+fn build_window(size: [f64; 2], args: &Args, exit_on_esc: bool) -> PistonWindow {
+    WindowSettings::new("Rusty Snake", window_size(size, args))
+        .fullscreen(args.fullscreen)
+        .exit_on_esc(exit_on_esc).build()
+        .unwrap_or_else(|e| {
+            panic!("Failed to build PistonWindow: {}", e)
+        })
+}
+
+fn load_assets(window: &mut PistonWindow, theme: Option<&str>) -> Assets {
+    let theme = match theme {
+        Some(name) => Theme::find(name).unwrap_or_else(|e| {
+            fail(1, &format!("Failed to load theme {}: {}", name, e))
+        }),
+        None => Theme::classic(),
+    };
+    Assets::load(&mut window.factory, theme).unwrap_or_else(|e| {
+        fail(1, &format!("Failed to load assets: {}", e))
+    })
+}
+
+// switches to the theme after the current one, keeping the current one
+// when the next fails to load
+fn next_theme(window: &mut PistonWindow, assets: &mut Assets) {
+    let next = assets.get_theme().get_next_name();
+    let result = Theme::find(&next).map_err(|e| e.to_string())
+            .and_then(|theme| assets.switch(&mut window.factory, theme)
+                    .map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        eprintln!("Failed to load theme {}: {}", next, e);
+    }
+}
+
+/// The name controls give `button`: the key's name, `Pad0-Button3` for a
+/// gamepad button or `Pad0-Hat0-Up` for a d-pad. Mouse buttons have none.
+fn button_name(button: &Button) -> Option<String> {
+    match button {
+        Button::Keyboard(key) => Some(format!("{:?}", key)),
+        Button::Controller(pad) => Some(format!("Pad{}-Button{}", pad.id, pad.button)),
+        Button::Hat(hat) => Some(format!("Pad{}-Hat{}-{:?}", hat.id, hat.which, hat.state)),
+        Button::Mouse(_) => None,
+    }
+}
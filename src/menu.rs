@@ -0,0 +1,188 @@
+use crate::config::Config;
+use crate::theme::Theme;
+
+
+// steps per second, changed half a step at a time
+const MIN_SPEED: f64 = 1.0;
+const MAX_SPEED: f64 = 20.0;
+const SPEED_STEP: f64 = 0.5;
+const MIN_WIDTH: u32 = 12;
+const MAX_WIDTH: u32 = 80;
+const MIN_HEIGHT: u32 = 12;
+const MAX_HEIGHT: u32 = 60;
+const SIZE_STEP: u32 = 1;
+pub const SETTINGS: [Setting; 5] = [
+    Setting::Speed,
+    Setting::Width,
+    Setting::Height,
+    Setting::Theme,
+    Setting::Smooth,
+];
+
+
+/// A titled list of entries to pick from, one of them selected. Moving
+/// past either end wraps around to the other one.
+pub struct Menu {
+    title: String,
+    entries: Vec<String>,
+    selected: usize,
+    cursor: bool,
+    hint: String,
+    message: Option<String>,
+}
+
+/// What the settings screen changes, a step at a time.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Setting {
+    /// Steps per second at the start of a round
+    Speed,
+    /// Board width in blocks, border included
+    Width,
+    /// Board height in blocks, border included
+    Height,
+    Theme,
+    /// Whether the snakes glide between blocks
+    Smooth,
+}
+
+
+impl Menu {
+
+    pub fn new(title: &str, entries: Vec<String>) -> Menu {
+        Menu {
+            title: String::from(title),
+            entries,
+            selected: 0,
+            cursor: true,
+            hint: String::new(),
+            message: None,
+        }
+    }
+
+    /// A line of help under the entries, e.g. the keys to use.
+    pub fn with_hint(mut self, hint: &str) -> Menu {
+        self.hint = String::from(hint);
+        self
+    }
+
+    pub fn with_selected(mut self, index: usize) -> Menu {
+        self.selected = index.min(self.entries.len().saturating_sub(1));
+        self
+    }
+
+    /// Shows the entries as a plain list, with none of them selected.
+    pub fn without_cursor(mut self) -> Menu {
+        self.cursor = false;
+        self
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.entries.is_empty() {
+            self.selected = (self.selected + 1) % self.entries.len();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if !self.entries.is_empty() {
+            self.selected = (self.selected + self.entries.len() - 1)
+                    % self.entries.len();
+        }
+    }
+
+    pub fn get_title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn get_entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// The index of the selected entry, `None` when the menu has no
+    /// cursor.
+    pub fn get_selected(&self) -> Option<usize> {
+        Some(self.selected).filter(|_| self.cursor && !self.entries.is_empty())
+    }
+
+    pub fn get_hint(&self) -> &str {
+        &self.hint
+    }
+
+    pub fn set_entry(&mut self, index: usize, entry: String) {
+        if let Some(current) = self.entries.get_mut(index) {
+            *current = entry;
+        }
+    }
+
+    pub fn get_message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// Shows `message` above the hint, e.g. why the selected entry could
+    /// not be used, until it is set again.
+    pub fn set_message(&mut self, message: Option<String>) {
+        self.message = message;
+    }
+
+}
+
+
+impl Setting {
+
+    /// The entry of the settings menu, with the current value of the
+    /// setting in `config`.
+    pub fn get_label(&self, config: &Config) -> String {
+        match self {
+            Setting::Speed => format!("Speed: {} steps/s", get_speed(config)),
+            Setting::Width => format!("Board width: {}", config.playground.width),
+            Setting::Height => format!("Board height: {}", config.playground.height),
+            Setting::Theme => format!("Theme: {}",
+                    config.theme.as_deref().unwrap_or("classic")),
+            Setting::Smooth => format!("Smooth movement: {}",
+                    if config.smooth { "on" } else { "off" }),
+        }
+    }
+
+    /// Moves the setting `steps` steps up, or down when negative, within
+    /// its range. Themes go round the installed ones.
+    pub fn adjust(&self, config: &mut Config, steps: i32) {
+        match self {
+            Setting::Speed => {
+                let speed = get_speed(config) + f64::from(steps) * SPEED_STEP;
+                let speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+                config.timing.move_delay = 1.0 / speed;
+            },
+            Setting::Width => {
+                config.playground.width = step_size(config.playground.width,
+                        steps, MIN_WIDTH, MAX_WIDTH);
+            },
+            Setting::Height => {
+                config.playground.height = step_size(config.playground.height,
+                        steps, MIN_HEIGHT, MAX_HEIGHT);
+            },
+            Setting::Theme => {
+                let names = Theme::names();
+                let current = config.theme.as_ref()
+                        .and_then(|theme| names.iter().position(|name| name == theme))
+                        .unwrap_or(0);
+                let count = names.len() as i64;
+                let next = (current as i64 + i64::from(steps)).rem_euclid(count);
+                config.theme = Some(names[next as usize].clone());
+            },
+            Setting::Smooth => config.smooth = !config.smooth,
+        }
+    }
+
+}
+
+
+
+// the speed of `config` to the nearest step, as the menu shows it
+fn get_speed(config: &Config) -> f64 {
+    let speed = 1.0 / config.timing.move_delay;
+    (speed / SPEED_STEP).round() * SPEED_STEP
+}
+
+fn step_size(size: u32, steps: i32, min: u32, max: u32) -> u32 {
+    let size = i64::from(size) + i64::from(steps) * i64::from(SIZE_STEP);
+    size.clamp(i64::from(min), i64::from(max)) as u32
+}
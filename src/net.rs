@@ -0,0 +1,639 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use crate::config::Config;
+use crate::game::{Game, Status};
+use crate::grid::{Position, Direction};
+
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+const PROTOCOL_MAGIC: &str = "rusty-snake-net";
+pub const PROTOCOL_VERSION: u32 = 2;
+
+
+/// What a client tells the server. Every message is a single line of
+/// text.
+pub enum ClientMessage {
+    /// Joins the lobby, or takes back the seat of `token` after losing
+    /// the connection
+    Hello { version: u32, name: String, token: Option<u64> },
+    Ready(bool),
+    Turn(Direction),
+    Bye,
+}
+
+/// What the server tells its clients. Every message is a single line of
+/// text, except `Config` which is followed by the lines of its TOML,
+/// without the host's controls.
+pub enum ServerMessage {
+    /// The client got a seat; `token` reclaims it after a reconnect
+    Welcome { token: u64 },
+    Refused(String),
+    Lobby(Vec<Seat>),
+    /// The settings of the game about to start
+    Config(Box<Config>),
+    /// The game starts, with the client steering the snake of `player`
+    Start { player: usize },
+    State(Snapshot),
+}
+
+/// A player waiting in the lobby or taking part in a game.
+#[derive(Clone)]
+pub struct Seat {
+    pub name: String,
+    pub state: SeatState,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum SeatState {
+    Waiting,
+    Ready,
+    Playing,
+    /// Lost the connection during a game; the snake goes on straight
+    Away,
+}
+
+/// Everything that changes in a `Game` from one tick to the next, sent by
+/// the server after every tick so clients only ever render its state.
+pub struct Snapshot {
+    pub ticks: u64,
+    pub status: Status,
+    pub winner: Option<usize>,
+    pub move_delay: f64,
+    pub food: Position,
+    pub bonus: Option<Position>,
+    pub snakes: Vec<SnakeState>,
+}
+
+pub struct SnakeState {
+    pub alive: bool,
+    pub score: u32,
+    pub direction: Direction,
+    /// Head first
+    pub blocks: Vec<Position>,
+}
+
+/// A client's link to the server. Messages are read on a thread of their
+/// own and picked up with `poll`, so the window never waits on the
+/// network.
+pub struct Connection {
+    stream: TcpStream,
+    messages: Receiver<ServerMessage>,
+}
+
+#[derive(Debug)]
+pub enum NetError {
+    Io(io::Error),
+    Protocol(String),
+}
+
+
+impl ClientMessage {
+
+    /// Reads the next message, or `None` once the client hung up.
+    pub fn read<R: BufRead>(reader: &mut R)
+            -> Result<Option<ClientMessage>, NetError> {
+        let line = match read_line(reader)? {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let message = match fields.as_slice() {
+            [magic, version, name] if *magic == PROTOCOL_MAGIC => {
+                ClientMessage::Hello {
+                    version: parse_field(version)?,
+                    name: String::from(*name),
+                    token: None,
+                }
+            },
+            [magic, version, name, token] if *magic == PROTOCOL_MAGIC => {
+                ClientMessage::Hello {
+                    version: parse_field(version)?,
+                    name: String::from(*name),
+                    token: Some(parse_field(token)?),
+                }
+            },
+            ["ready"] => ClientMessage::Ready(true),
+            ["unready"] => ClientMessage::Ready(false),
+            ["turn", direction] => ClientMessage::Turn(parse_field(direction)?),
+            ["bye"] => ClientMessage::Bye,
+            _ => return Err(protocol_error(&format!("unknown message '{}'", line))),
+        };
+        Ok(Some(message))
+    }
+
+}
+
+
+impl fmt::Display for ClientMessage {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientMessage::Hello { version, name, token: None } =>
+                writeln!(f, "{} {} {}", PROTOCOL_MAGIC, version, name),
+            ClientMessage::Hello { version, name, token: Some(token) } =>
+                writeln!(f, "{} {} {} {}", PROTOCOL_MAGIC, version, name, token),
+            ClientMessage::Ready(true) => writeln!(f, "ready"),
+            ClientMessage::Ready(false) => writeln!(f, "unready"),
+            ClientMessage::Turn(direction) => writeln!(f, "turn {}", direction),
+            ClientMessage::Bye => writeln!(f, "bye"),
+        }
+    }
+
+}
+
+
+impl ServerMessage {
+
+    /// Reads the next message, or `None` once the server hung up.
+    pub fn read<R: BufRead>(reader: &mut R)
+            -> Result<Option<ServerMessage>, NetError> {
+        let line = match read_line(reader)? {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+        let mut parts = line.splitn(2, ' ');
+        let kind = parts.next().unwrap_or("");
+        let rest = parts.next().unwrap_or("").trim();
+        let message = match kind {
+            "welcome" => ServerMessage::Welcome { token: parse_field(rest)? },
+            "refused" => ServerMessage::Refused(String::from(rest)),
+            "lobby" => ServerMessage::Lobby(rest.split_whitespace()
+                    .map(Seat::parse)
+                    .collect::<Result<_, _>>()?),
+            "config" => {
+                let count: usize = parse_field(rest)?;
+                let mut text = String::new();
+                for _ in 0..count {
+                    let line = read_line(reader)?
+                            .ok_or_else(|| protocol_error("truncated config"))?;
+                    text.push_str(&line);
+                    text.push('\n');
+                }
+                let config = Config::parse(&text)
+                        .map_err(|e| protocol_error(&e.to_string()))?;
+                ServerMessage::Config(Box::new(config))
+            },
+            "start" => ServerMessage::Start { player: parse_field(rest)? },
+            "state" => ServerMessage::State(Snapshot::parse(rest)?),
+            _ => return Err(protocol_error(&format!("unknown message '{}'", line))),
+        };
+        Ok(Some(message))
+    }
+
+}
+
+
+impl fmt::Display for ServerMessage {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ServerMessage::Welcome { token } => writeln!(f, "welcome {}", token),
+            ServerMessage::Refused(reason) => writeln!(f, "refused {}", reason),
+            ServerMessage::Lobby(seats) => {
+                write!(f, "lobby")?;
+                for seat in seats.iter() {
+                    write!(f, " {}", seat)?;
+                }
+                writeln!(f)
+            },
+            ServerMessage::Config(config) => {
+                // every player keeps their own buttons
+                let mut value = toml::Value::try_from(config).map_err(|_| fmt::Error)?;
+                if let Some(table) = value.as_table_mut() {
+                    table.remove("controls");
+                }
+                let text = toml::to_string(&value).map_err(|_| fmt::Error)?;
+                writeln!(f, "config {}", text.lines().count())?;
+                for line in text.lines() {
+                    writeln!(f, "{}", line)?;
+                }
+                Ok(())
+            },
+            ServerMessage::Start { player } => writeln!(f, "start {}", player),
+            ServerMessage::State(snapshot) => writeln!(f, "state {}", snapshot),
+        }
+    }
+
+}
+
+
+impl Seat {
+
+    fn parse(text: &str) -> Result<Seat, NetError> {
+        let mut parts = text.rsplitn(2, ':');
+        let state = match parts.next() {
+            Some("waiting") => SeatState::Waiting,
+            Some("ready") => SeatState::Ready,
+            Some("playing") => SeatState::Playing,
+            Some("away") => SeatState::Away,
+            _ => return Err(protocol_error(&format!("invalid seat '{}'", text))),
+        };
+        let name = parts.next()
+                .ok_or_else(|| protocol_error(&format!("invalid seat '{}'", text)))?;
+        Ok(Seat { name: String::from(name), state })
+    }
+
+}
+
+
+impl fmt::Display for Seat {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.name, self.state)
+    }
+
+}
+
+
+impl fmt::Display for SeatState {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            SeatState::Waiting => "waiting",
+            SeatState::Ready => "ready",
+            SeatState::Playing => "playing",
+            SeatState::Away => "away",
+        };
+        write!(f, "{}", name)
+    }
+
+}
+
+
+impl Snapshot {
+
+    pub fn capture(game: &Game) -> Snapshot {
+        let snakes = game.get_snakes().iter().enumerate()
+                .map(|(player, snake)| SnakeState {
+                    alive: game.is_alive(player),
+                    score: game.get_scores()[player],
+                    direction: snake.get_direction().clone(),
+                    blocks: Some(snake.get_head_position()).into_iter()
+                            .chain(snake.get_body().map(|block| block.get_position()))
+                            .cloned()
+                            .collect(),
+                })
+                .collect();
+        Snapshot {
+            ticks: game.get_ticks(),
+            status: game.get_status().clone(),
+            winner: game.get_winner(),
+            move_delay: game.get_move_delay(),
+            food: game.get_food().get_position().clone(),
+            bonus: game.get_bonus().map(|bonus| bonus.get_position().clone()),
+            snakes,
+        }
+    }
+
+    // `ticks status winner move_delay food bonus`, then a `| alive score
+    // direction blocks...` group for every snake
+    fn parse(text: &str) -> Result<Snapshot, NetError> {
+        let mut groups = text.split('|');
+        let fields: Vec<&str> = groups.next().unwrap_or("")
+                .split_whitespace().collect();
+        if fields.len() != 6 {
+            return Err(protocol_error("a state expects 6 values"));
+        }
+        let mut snakes = Vec::new();
+        for group in groups {
+            let fields: Vec<&str> = group.split_whitespace().collect();
+            if fields.len() < 4 {
+                return Err(protocol_error("a snake expects at least 4 values"));
+            }
+            snakes.push(SnakeState {
+                alive: fields[0] == "1",
+                score: parse_field(fields[1])?,
+                direction: parse_field(fields[2])?,
+                blocks: fields[3..].iter()
+                        .map(|block| parse_position(block))
+                        .collect::<Result<_, _>>()?,
+            });
+        }
+        Ok(Snapshot {
+            ticks: parse_field(fields[0])?,
+            status: parse_status(fields[1])?,
+            winner: parse_optional(fields[2], parse_field)?,
+            move_delay: parse_field(fields[3])?,
+            food: parse_position(fields[4])?,
+            bonus: parse_optional(fields[5], parse_position)?,
+            snakes,
+        })
+    }
+
+}
+
+
+impl fmt::Display for Snapshot {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self.status {
+            Status::Running => "running",
+            Status::Paused => "paused",
+            Status::GameOver => "gameover",
+            Status::Cleared => "cleared",
+        };
+        write!(f, "{} {} ", self.ticks, status)?;
+        match self.winner {
+            Some(winner) => write!(f, "{} ", winner)?,
+            None => write!(f, "- ")?,
+        }
+        let Position (column, row) = self.food;
+        write!(f, "{} {},{} ", self.move_delay, column, row)?;
+        match &self.bonus {
+            Some(Position (column, row)) => write!(f, "{},{}", column, row)?,
+            None => write!(f, "-")?,
+        }
+        for snake in self.snakes.iter() {
+            write!(f, " | {} {} {}", if snake.alive { 1 } else { 0 },
+                    snake.score, snake.direction)?;
+            for Position (column, row) in snake.blocks.iter() {
+                write!(f, " {},{}", column, row)?;
+            }
+        }
+        Ok(())
+    }
+
+}
+
+
+impl Connection {
+
+    /// Connects to the server at `address` and says hello, asking for the
+    /// seat of `token` back if there is one.
+    pub fn open<A: ToSocketAddrs>(address: A, name: &str, token: Option<u64>)
+            -> Result<Connection, NetError> {
+        let mut stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            loop {
+                match ServerMessage::read(&mut reader) {
+                    Ok(Some(message)) => {
+                        if sender.send(message).is_err() {
+                            break;
+                        }
+                    },
+                    Ok(None) => break,
+                    Err(e) => {
+                        eprintln!("Dropping the connection to the server: {}", e);
+                        break;
+                    },
+                }
+            }
+        });
+        let hello = ClientMessage::Hello {
+            version: PROTOCOL_VERSION,
+            name: clean_name(name),
+            token,
+        };
+        send(&mut stream, &hello)?;
+        Ok(Connection { stream, messages })
+    }
+
+    /// Like `open`, on a thread of its own; the connection, or why there
+    /// is none, arrives on the returned channel.
+    pub fn open_in_background(address: &str, name: &str, token: Option<u64>)
+            -> Receiver<Result<Connection, NetError>> {
+        let (sender, connection) = mpsc::channel();
+        let (address, name) = (String::from(address), String::from(name));
+        thread::spawn(move || {
+            let _ = sender.send(Connection::open(address, &name, token));
+        });
+        connection
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) -> io::Result<()> {
+        send(&mut self.stream, message)
+    }
+
+    /// The next message received, if any. Fails once the server is gone.
+    pub fn poll(&self) -> Result<Option<ServerMessage>, NetError> {
+        match self.messages.try_recv() {
+            Ok(message) => Ok(Some(message)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(NetError::Io(
+                    io::Error::from(io::ErrorKind::ConnectionAborted))),
+        }
+    }
+
+}
+
+
+impl Drop for Connection {
+
+    fn drop(&mut self) {
+        let _ = send(&mut self.stream, &ClientMessage::Bye);
+    }
+
+}
+
+
+impl fmt::Display for NetError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetError::Io(error) => write!(f, "{}", error),
+            NetError::Protocol(message) => write!(f, "{}", message),
+        }
+    }
+
+}
+
+
+impl std::error::Error for NetError {}
+
+
+impl From<io::Error> for NetError {
+
+    fn from(error: io::Error) -> NetError {
+        NetError::Io(error)
+    }
+
+}
+
+
+
+/// Writes `message` to `stream` in one go.
+pub fn send<M: fmt::Display>(stream: &mut TcpStream, message: &M)
+        -> io::Result<()> {
+    stream.write_all(message.to_string().as_bytes())
+}
+
+/// Turns a player name into a single word that fits in the protocol.
+pub fn clean_name(name: &str) -> String {
+    let name: String = name.chars()
+            .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+            .take(12)
+            .collect();
+    if name.is_empty() {
+        String::from("Player")
+    } else {
+        name
+    }
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> Result<Option<String>, NetError> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(String::from(line.trim_end())))
+}
+
+fn parse_status(value: &str) -> Result<Status, NetError> {
+    match value {
+        "running" => Ok(Status::Running),
+        "paused" => Ok(Status::Paused),
+        "gameover" => Ok(Status::GameOver),
+        "cleared" => Ok(Status::Cleared),
+        _ => Err(protocol_error(&format!("invalid status '{}'", value))),
+    }
+}
+
+fn parse_position(value: &str) -> Result<Position, NetError> {
+    let mut parts = value.splitn(2, ',');
+    match (parts.next(), parts.next()) {
+        (Some(column), Some(row)) => {
+            Ok(Position::new(parse_field(column)?, parse_field(row)?))
+        },
+        _ => Err(protocol_error(&format!("invalid position '{}'", value))),
+    }
+}
+
+fn parse_optional<T, F>(value: &str, parse: F) -> Result<Option<T>, NetError>
+        where F: Fn(&str) -> Result<T, NetError> {
+    if value == "-" {
+        Ok(None)
+    } else {
+        parse(value).map(Some)
+    }
+}
+
+fn parse_field<T: std::str::FromStr>(value: &str) -> Result<T, NetError> {
+    value.parse().map_err(|_| protocol_error(
+            &format!("invalid value '{}'", value)))
+}
+
+fn protocol_error(message: &str) -> NetError {
+    NetError::Protocol(String::from(message))
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::io::Cursor;
+
+    fn client_round_trip(message: &ClientMessage) -> ClientMessage {
+        let mut reader = Cursor::new(message.to_string());
+        ClientMessage::read(&mut reader).unwrap().unwrap()
+    }
+
+    fn server_round_trip(message: &ServerMessage) -> ServerMessage {
+        let mut reader = Cursor::new(message.to_string());
+        let read = ServerMessage::read(&mut reader).unwrap().unwrap();
+        assert!(ServerMessage::read(&mut reader).unwrap().is_none());
+        read
+    }
+
+    #[test]
+    fn client_messages_survive_a_round_trip() {
+        let messages = [
+            ClientMessage::Hello { version: PROTOCOL_VERSION,
+                    name: String::from("ann"), token: None },
+            ClientMessage::Hello { version: PROTOCOL_VERSION,
+                    name: String::from("bob"), token: Some(u64::MAX) },
+            ClientMessage::Ready(true),
+            ClientMessage::Ready(false),
+            ClientMessage::Turn(Direction::Left),
+            ClientMessage::Bye,
+        ];
+        for message in messages.iter() {
+            assert_eq!(client_round_trip(message).to_string(), message.to_string());
+        }
+    }
+
+    #[test]
+    fn server_messages_survive_a_round_trip() {
+        let messages = [
+            ServerMessage::Welcome { token: 42 },
+            ServerMessage::Refused(String::from("the lobby is full")),
+            ServerMessage::Lobby(vec![
+                Seat { name: String::from("ann"), state: SeatState::Ready },
+                Seat { name: String::from("bob"), state: SeatState::Away },
+            ]),
+            ServerMessage::Lobby(Vec::new()),
+            ServerMessage::Start { player: 3 },
+        ];
+        for message in messages.iter() {
+            assert_eq!(server_round_trip(message).to_string(), message.to_string());
+        }
+    }
+
+    #[test]
+    fn config_goes_over_without_the_controls() {
+        let mut config = Config::default();
+        config.playground.width = 41;
+        config.set_player_count(3);
+        config.controls.players[0].up = vec![String::from("F1")];
+        let message = ServerMessage::Config(Box::new(config));
+        let read = match server_round_trip(&message) {
+            ServerMessage::Config(config) => config,
+            _ => panic!("expected a config"),
+        };
+        assert_eq!(read.playground.width, 41);
+        assert_eq!(read.rivals.len(), 2);
+        assert_eq!(read.controls.players[0].up, Config::default().controls.players[0].up);
+    }
+
+    #[test]
+    fn snapshots_restore_the_game_they_were_taken_of() {
+        let mut config = Config::default();
+        config.set_player_count(2);
+        let mut game = config.build_game_with_seed(9);
+        for _ in 0..5 {
+            game.tick();
+        }
+        let snapshot = Snapshot::capture(&game);
+        let message = server_round_trip(&ServerMessage::State(snapshot));
+        let snapshot = match message {
+            ServerMessage::State(snapshot) => snapshot,
+            _ => panic!("expected a state"),
+        };
+        assert_eq!(snapshot.to_string(), Snapshot::capture(&game).to_string());
+        let mut copy = config.build_game_with_seed(1);
+        copy.restore(&snapshot);
+        assert_eq!(Snapshot::capture(&copy).to_string(), snapshot.to_string());
+        assert_eq!(copy.get_ticks(), 5);
+        assert!(*copy.get_food().get_position() == *game.get_food().get_position());
+    }
+
+    #[test]
+    fn malformed_lines_are_protocol_errors() {
+        let lines = ["dance\n", "turn sideways\n", "rusty-snake-net x ann\n"];
+        for line in lines.iter() {
+            let result = ClientMessage::read(&mut Cursor::new(*line));
+            assert!(matches!(result, Err(NetError::Protocol(_))));
+        }
+        let lines = ["welcome soon\n", "lobby ann\n", "state 1 running - 0.3 1,1\n",
+                "config 3\nfoo = 1\n"];
+        for line in lines.iter() {
+            let result = ServerMessage::read(&mut Cursor::new(*line));
+            assert!(matches!(result, Err(NetError::Protocol(_))));
+        }
+    }
+
+    #[test]
+    fn names_are_cleaned_to_one_short_word() {
+        assert_eq!(clean_name("Ann Lee!"), "AnnLee");
+        assert_eq!(clean_name("a_very-long-name-indeed"), "a_very-long-");
+        assert_eq!(clean_name("  "), "Player");
+    }
+
+}
@@ -1,13 +1,6 @@
-use piston_window::{GfxFactory, Context, G2d};
-use piston_window::types::Color;
-use crate::draw::{Position, draw_rectangle};
-
-
-const PLAYGROUND_WIDTH: u32 = 35;
-const PLAYGROUND_HEIGHT: u32 = 25;
-const PLAYGROUND_COLOR: Color = [0.66, 0.78, 0.22, 1.0];
-const BORDER_COLOR: Color = [0.80, 0.30, 0.30, 1.0];
-const BORDER_WIDTH: u32 = 1;
+use std::collections::HashSet;
+use crate::config::PlaygroundConfig;
+use crate::grid::{Position, Direction, Color};
 
 
 pub struct Playground {
@@ -16,18 +9,16 @@ pub struct Playground {
     color: Color,
     border_color: Color,
     border_width: u32,
+    wrap: bool,
+    walls: HashSet<Position>,
+    food_zone: Vec<Position>,
 }
 
 
 impl Default for Playground {
 
     fn default() -> Playground {
-        Playground::new(
-            PLAYGROUND_WIDTH, 
-            PLAYGROUND_HEIGHT, 
-            PLAYGROUND_COLOR, 
-            BORDER_COLOR,
-            BORDER_WIDTH)
+        PlaygroundConfig::default().build()
     }
 
 }
@@ -36,7 +27,7 @@ impl Default for Playground {
 impl Playground {
 
     pub fn new(width: u32, height: u32, color: Color,
-            border_color: Color, border_width: u32)
+            border_color: Color, border_width: u32, wrap: bool)
             -> Playground {
         Playground {
             width,
@@ -44,27 +35,68 @@ impl Playground {
             color,
             border_color,
             border_width,
+            wrap,
+            walls: HashSet::new(),
+            food_zone: Vec::new(),
+        }
+    }
+
+    /// Adds wall blocks inside the playground, on top of its border.
+    pub fn with_walls(mut self, walls: Vec<Position>) -> Playground {
+        self.walls.extend(walls);
+        self
+    }
+
+    /// Restricts food to the given blocks.
+    pub fn with_food_zone(mut self, food_zone: Vec<Position>) -> Playground {
+        self.food_zone = food_zone;
+        self
+    }
+
+    /// Where a snake at `position` ends up after one step towards
+    /// `direction`. On a wrapping playground the border is passable and
+    /// the snake comes back in on the opposite side.
+    pub fn next_position(&self, position: &Position, direction: &Direction)
+            -> Position {
+        let (columns, rows) = direction.offset();
+        if !self.wrap {
+            return position.shifted_by(columns, rows, self.width, self.height);
         }
+        // wrap around the inside of the border, not the whole board
+        let border = self.border_width;
+        let Position (column, row) = *position;
+        let Position (column, row) = Position::new(column - border, row - border)
+                .shifted_by(columns, rows,
+                    self.width - border * 2,
+                    self.height - border * 2);
+        Position::new(column + border, row + border)
     }
 
-    pub fn draw(&self, _factory: &mut GfxFactory,
-            context: &Context, graphics: &mut G2d) {
-        draw_rectangle(
-            &Position (0, 0), 
-            self.width, 
-            self.height, 
-            self.border_color, 
-            context, 
-            graphics
-        );
-        draw_rectangle(
-            &Position (self.border_width, self.border_width), 
-            self.width - (self.border_width * 2), 
-            self.height - (self.border_width * 2), 
-            self.color, 
-            context, 
-            graphics
-        );
+    pub fn is_wall(&self, position: &Position) -> bool {
+        self.walls.contains(position) || self.on_border(position)
+    }
+
+    /// Whether `position` is one of the border blocks. On a wrapping
+    /// playground the border is passable and never counts as a wall.
+    pub fn on_border(&self, position: &Position) -> bool {
+        !self.wrap && self.in_border(position)
+    }
+
+    /// Whether `position` lies in the border, passable or not.
+    pub fn in_border(&self, position: &Position) -> bool {
+        let Position (column, row) = *position;
+        let border = self.border_width;
+        column < border || row < border
+                || column >= self.width - border
+                || row >= self.height - border
+    }
+
+    pub fn get_walls(&self) -> impl Iterator<Item = &Position> {
+        self.walls.iter()
+    }
+
+    pub fn get_food_zone(&self) -> &[Position] {
+        &self.food_zone
     }
 
     pub fn get_width(&self) -> u32 {
@@ -79,4 +111,16 @@ impl Playground {
         self.border_width
     }
 
+    pub fn is_wrapping(&self) -> bool {
+        self.wrap
+    }
+
+    pub fn get_color(&self) -> Color {
+        self.color
+    }
+
+    pub fn get_border_color(&self) -> Color {
+        self.border_color
+    }
+
 }
\ No newline at end of file
@@ -0,0 +1,533 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::campaign::{Campaign, Target};
+use crate::config::{Config, SnakeConfig};
+use crate::game::Game;
+use crate::grid::{Position, Direction};
+
+
+const REPLAY_MAGIC: &str = "rusty-snake-replay";
+const REPLAY_VERSION: u32 = 1;
+
+
+/// Everything needed to rebuild a round of `Game` and feed every snake the
+/// same turns on the same ticks.
+#[derive(Clone)]
+pub struct Replay {
+    seed: u64,
+    config: Config,
+    /// The campaign stage the round was played on, by name
+    stage: Option<String>,
+    target: Option<Target>,
+    turns: Vec<(u64, usize, Direction)>,
+    final_score: u32,
+    final_ticks: u64,
+}
+
+/// Plays a `Replay` back in real time, e.g. inside the window.
+pub struct Playback {
+    replay: Replay,
+    game: Game,
+    next_turn: usize,
+    waiting_time: f64,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Format(String),
+    UnsupportedVersion(u32),
+}
+
+
+impl Replay {
+
+    /// Starts recording the current round of `game`. Must be called before
+    /// the first tick of the round.
+    pub fn record(game: &Game) -> Replay {
+        let playground = game.get_playground();
+        let snake = game.get_snake();
+        let mut config = Config::default();
+        config.playground.width = playground.get_width();
+        config.playground.height = playground.get_height();
+        config.playground.border_width = playground.get_border_width();
+        config.playground.wrap = playground.is_wrapping();
+        config.playground.walls = playground.get_walls().cloned().collect();
+        config.playground.walls.sort_by_key(|Position (column, row)| (*row, *column));
+        config.playground.food_zone = playground.get_food_zone().to_vec();
+        config.snake.init_position = snake.get_init_position().clone();
+        config.snake.init_direction = snake.get_init_direction().clone();
+        config.snake.init_length = snake.get_init_length();
+        config.snake.bot = bot_name(game, 0);
+        config.rivals = game.get_snakes()[1..].iter().enumerate()
+                .map(|(index, rival)| {
+                    let mut rival_config = config.snake.mirrored(
+                            index, &config.playground);
+                    rival_config.init_position = rival.get_init_position().clone();
+                    rival_config.init_direction = rival.get_init_direction().clone();
+                    rival_config.init_length = rival.get_init_length();
+                    rival_config.bot = bot_name(game, index + 1);
+                    rival_config
+                })
+                .collect();
+        config.timing.move_delay = game.get_init_move_delay();
+        config.food.calories = game.get_food().get_calories();
+        config.bonus.calories = game.get_bonus_food().get_calories();
+        if let Some(time) = game.get_bonus_food().get_disappear_after() {
+            config.bonus.disappear_after = time;
+        }
+        Replay {
+            seed: game.get_seed(),
+            config,
+            stage: None,
+            target: game.get_target().cloned(),
+            turns: Vec::new(),
+            final_score: 0,
+            final_ticks: 0,
+        }
+    }
+
+    /// Names the stage of `campaign` the round is played on, if any.
+    pub fn with_campaign(mut self, campaign: Option<&Campaign>) -> Replay {
+        self.stage = campaign.map(|campaign| String::from(
+                campaign.get_stage().get_name()));
+        self
+    }
+
+    /// Records a turn `player` requested while `game` was at its current
+    /// tick.
+    pub fn record_turn(&mut self, game: &Game, player: usize,
+            direction: Direction) {
+        self.turns.push((game.get_ticks(), player, direction));
+    }
+
+    pub fn finish(&mut self, game: &Game) {
+        self.final_score = game.get_score();
+        self.final_ticks = game.get_ticks();
+    }
+
+    pub fn new_game(&self) -> Game {
+        let mut game = self.config.build_game_with_seed(self.seed);
+        game.set_target(self.target.clone());
+        game
+    }
+
+    /// Runs the whole replay without a window and returns the final game.
+    pub fn play(&self) -> Game {
+        let mut game = self.new_game();
+        let mut next_turn = 0;
+        while game.get_ticks() < self.final_ticks {
+            if game.is_finished() {
+                break;
+            }
+            next_turn = self.feed_turns(&mut game, next_turn);
+            game.tick();
+        }
+        game
+    }
+
+    /// Plays the replay headlessly and checks it reaches the recorded
+    /// score on the recorded tick.
+    pub fn verify(&self) -> bool {
+        let game = self.play();
+        game.get_score() == self.final_score
+                && game.get_ticks() == self.final_ticks
+    }
+
+    fn feed_turns(&self, game: &mut Game, mut next_turn: usize) -> usize {
+        while let Some((tick, player, direction)) = self.turns.get(next_turn) {
+            if *tick != game.get_ticks() {
+                break;
+            }
+            game.turn_snake(*player, direction.clone());
+            next_turn += 1;
+        }
+        next_turn
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn get_stage(&self) -> Option<&str> {
+        self.stage.as_deref()
+    }
+
+    pub fn get_target(&self) -> Option<&Target> {
+        self.target.as_ref()
+    }
+
+    pub fn get_final_score(&self) -> u32 {
+        self.final_score
+    }
+
+    pub fn get_final_ticks(&self) -> u64 {
+        self.final_ticks
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, ReplayError> {
+        let text = fs::read_to_string(path)?;
+        Replay::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Replay, ReplayError> {
+        let mut lines = text.lines().enumerate()
+                .map(|(index, line)| (index + 1, line.trim()))
+                .filter(|(_, line)| !line.is_empty());
+
+        let (number, header) = lines.next()
+                .ok_or_else(|| format_error(1, "empty file"))?;
+        let fields: Vec<&str> = header.split_whitespace().collect();
+        if fields.len() != 2 || fields[0] != REPLAY_MAGIC {
+            return Err(format_error(number, "not a replay file"));
+        }
+        let version = parse_field(number, fields[1])?;
+        if version != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+
+        let mut replay = Replay {
+            seed: 0,
+            config: Config::default(),
+            stage: None,
+            target: None,
+            turns: Vec::new(),
+            final_score: 0,
+            final_ticks: 0,
+        };
+        for (number, line) in lines {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let values = &fields[1..];
+            let expect_between = |min: usize, max: usize| {
+                if (min..=max).contains(&values.len()) {
+                    Ok(())
+                } else if min == max {
+                    Err(format_error(number, &format!(
+                            "'{}' expects {} values", fields[0], min)))
+                } else {
+                    Err(format_error(number, &format!(
+                            "'{}' expects {} to {} values", fields[0], min, max)))
+                }
+            };
+            let expect = |count: usize| expect_between(count, count);
+            match fields[0] {
+                "seed" => {
+                    expect(1)?;
+                    replay.seed = parse_field(number, values[0])?;
+                },
+                "stage" => {
+                    if values.is_empty() {
+                        return Err(format_error(number, "'stage' expects a name"));
+                    }
+                    replay.stage = Some(values.join(" "));
+                },
+                "target" => {
+                    expect(2)?;
+                    let goal = parse_field(number, values[1])?;
+                    replay.target = Some(match values[0] {
+                        "score" => Target::Score(goal),
+                        "length" => Target::Length(goal),
+                        "foods" => Target::Foods(goal),
+                        kind => return Err(format_error(number,
+                                &format!("unknown target '{}'", kind))),
+                    });
+                },
+                "playground" => {
+                    expect(4)?;
+                    let playground = &mut replay.config.playground;
+                    playground.width = parse_field(number, values[0])?;
+                    playground.height = parse_field(number, values[1])?;
+                    playground.border_width = parse_field(number, values[2])?;
+                    playground.wrap = parse_field(number, values[3])?;
+                },
+                "snake" => {
+                    expect_between(4, 5)?;
+                    let snake = &mut replay.config.snake;
+                    snake.init_position = Position::new(
+                            parse_field(number, values[0])?,
+                            parse_field(number, values[1])?);
+                    snake.init_direction = parse_field(number, values[2])?;
+                    snake.init_length = parse_field(number, values[3])?;
+                    snake.bot = values.get(4).map(|bot| String::from(*bot));
+                },
+                "rival" => {
+                    expect_between(4, 5)?;
+                    let index = replay.config.rivals.len();
+                    let mut rival = replay.config.snake.mirrored(
+                            index, &replay.config.playground);
+                    rival.init_position = Position::new(
+                            parse_field(number, values[0])?,
+                            parse_field(number, values[1])?);
+                    rival.init_direction = parse_field(number, values[2])?;
+                    rival.init_length = parse_field(number, values[3])?;
+                    rival.bot = values.get(4).map(|bot| String::from(*bot));
+                    replay.config.rivals.push(rival);
+                },
+                "wall" => {
+                    expect(2)?;
+                    replay.config.playground.walls.push(Position::new(
+                            parse_field(number, values[0])?,
+                            parse_field(number, values[1])?));
+                },
+                "zone" => {
+                    expect(2)?;
+                    replay.config.playground.food_zone.push(Position::new(
+                            parse_field(number, values[0])?,
+                            parse_field(number, values[1])?));
+                },
+                "move_delay" => {
+                    expect(1)?;
+                    replay.config.timing.move_delay =
+                            parse_field(number, values[0])?;
+                },
+                "food" => {
+                    expect(1)?;
+                    replay.config.food.calories =
+                            parse_field(number, values[0])?;
+                },
+                "bonus" => {
+                    expect(2)?;
+                    let bonus = &mut replay.config.bonus;
+                    bonus.calories = parse_field(number, values[0])?;
+                    bonus.disappear_after = parse_field(number, values[1])?;
+                },
+                "turn" => {
+                    expect(3)?;
+                    let tick = parse_field(number, values[0])?;
+                    let direction = parse_field(number, values[1])?;
+                    let player = parse_field(number, values[2])?;
+                    replay.turns.push((tick, player, direction));
+                },
+                "score" => {
+                    expect(1)?;
+                    replay.final_score = parse_field(number, values[0])?;
+                },
+                "ticks" => {
+                    expect(1)?;
+                    replay.final_ticks = parse_field(number, values[0])?;
+                },
+                key => {
+                    return Err(format_error(number,
+                            &format!("unknown entry '{}'", key)));
+                },
+            }
+        }
+        replay.config.validate()
+                .map_err(|e| ReplayError::Format(e.to_string()))?;
+        Ok(replay)
+    }
+
+}
+
+
+impl fmt::Display for Replay {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let playground = &self.config.playground;
+        let snake = &self.config.snake;
+        let bonus = &self.config.bonus;
+        let Position (column, row) = snake.init_position;
+        writeln!(f, "{} {}", REPLAY_MAGIC, REPLAY_VERSION)?;
+        writeln!(f, "seed {}", self.seed)?;
+        if let Some(stage) = &self.stage {
+            writeln!(f, "stage {}", stage)?;
+        }
+        if let Some(target) = &self.target {
+            writeln!(f, "target {} {}", target.get_label().to_lowercase(),
+                    target.get_goal())?;
+        }
+        writeln!(f, "playground {} {} {} {}", playground.width,
+                playground.height, playground.border_width, playground.wrap)?;
+        for Position (column, row) in playground.walls.iter() {
+            writeln!(f, "wall {} {}", column, row)?;
+        }
+        for Position (column, row) in playground.food_zone.iter() {
+            writeln!(f, "zone {} {}", column, row)?;
+        }
+        write!(f, "snake {} {} {} {}",
+                column, row, snake.init_direction, snake.init_length)?;
+        write_bot(f, snake)?;
+        for rival in self.config.rivals.iter() {
+            let Position (column, row) = rival.init_position;
+            write!(f, "rival {} {} {} {}",
+                    column, row, rival.init_direction, rival.init_length)?;
+            write_bot(f, rival)?;
+        }
+        writeln!(f, "move_delay {}", self.config.timing.move_delay)?;
+        writeln!(f, "food {}", self.config.food.calories)?;
+        writeln!(f, "bonus {} {}", bonus.calories, bonus.disappear_after)?;
+        writeln!(f, "score {}", self.final_score)?;
+        writeln!(f, "ticks {}", self.final_ticks)?;
+        for (tick, player, direction) in self.turns.iter() {
+            writeln!(f, "turn {} {} {}", tick, direction, player)?;
+        }
+        Ok(())
+    }
+
+}
+
+
+impl Playback {
+
+    pub fn new(replay: Replay) -> Playback {
+        let game = replay.new_game();
+        Playback {
+            replay,
+            game,
+            next_turn: 0,
+            waiting_time: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f64) {
+        if self.is_finished() {
+            return;
+        }
+        self.waiting_time += delta_time;
+        while self.waiting_time >= self.game.get_move_delay()
+                && !self.is_finished() {
+            self.waiting_time -= self.game.get_move_delay();
+            self.next_turn = self.replay.feed_turns(
+                    &mut self.game, self.next_turn);
+            self.game.tick();
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.game.is_finished()
+                || self.game.get_ticks() >= self.replay.final_ticks
+    }
+
+    pub fn get_game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn get_replay(&self) -> &Replay {
+        &self.replay
+    }
+
+}
+
+
+impl fmt::Display for ReplayError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "{}", error),
+            ReplayError::Format(message) => write!(f, "{}", message),
+            ReplayError::UnsupportedVersion(version) =>
+                write!(f, "unsupported replay version {}", version),
+        }
+    }
+
+}
+
+
+impl std::error::Error for ReplayError {}
+
+
+impl From<io::Error> for ReplayError {
+
+    fn from(error: io::Error) -> ReplayError {
+        ReplayError::Io(error)
+    }
+
+}
+
+
+
+fn bot_name(game: &Game, player: usize) -> Option<String> {
+    game.get_controller(player)
+            .map(|controller| String::from(controller.name()))
+}
+
+fn write_bot(f: &mut fmt::Formatter, snake: &SnakeConfig) -> fmt::Result {
+    match &snake.bot {
+        Some(bot) => writeln!(f, " {}", bot),
+        None => writeln!(f),
+    }
+}
+
+fn parse_field<T: std::str::FromStr>(line: usize, value: &str)
+        -> Result<T, ReplayError> {
+    value.parse().map_err(|_| format_error(line,
+            &format!("invalid value '{}'", value)))
+}
+
+fn format_error(line: usize, message: &str) -> ReplayError {
+    ReplayError::Format(format!("line {}: {}", line, message))
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn replay_survives_a_round_trip() {
+        let mut game = Config::default().build_game_with_seed(7);
+        let mut replay = Replay::record(&game);
+        let turns = [(0, Direction::Down), (5, Direction::Right), (12, Direction::Up)];
+        while !game.is_finished() && game.get_ticks() < 500 {
+            let ticks = game.get_ticks();
+            for (_, direction) in turns.iter().filter(|(tick, _)| *tick == ticks) {
+                replay.record_turn(&game, 0, direction.clone());
+                game.turn(direction.clone());
+            }
+            game.tick();
+        }
+        replay.finish(&game);
+
+        let text = replay.to_string();
+        let parsed = Replay::parse(&text).unwrap();
+        assert_eq!(parsed.to_string(), text);
+        assert_eq!(parsed.get_seed(), 7);
+        assert_eq!(parsed.get_final_ticks(), game.get_ticks());
+        assert!(parsed.verify());
+    }
+
+    #[test]
+    fn campaign_replays_keep_their_stage_and_target() {
+        let campaign = Campaign::new(None);
+        let mut game = campaign.build_game_with_seed(&Config::default(), 3).unwrap();
+        let mut replay = Replay::record(&game).with_campaign(Some(&campaign));
+        let mut bot = crate::controller::by_name("bfs").unwrap();
+        while !game.is_finished() {
+            let direction = bot.next_direction(&game, 0);
+            replay.record_turn(&game, 0, direction.clone());
+            game.turn(direction);
+            game.tick();
+        }
+        assert!(matches!(game.get_status(), crate::game::Status::Cleared));
+        replay.finish(&game);
+
+        let parsed = Replay::parse(&replay.to_string()).unwrap();
+        assert_eq!(parsed.get_stage(), Some(campaign.get_stage().get_name()));
+        assert!(parsed.get_target() == Some(campaign.get_stage().get_target()));
+        assert!(parsed.verify());
+        assert!(matches!(parsed.play().get_status(), crate::game::Status::Cleared));
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let text = Replay::record(&Game::default()).to_string()
+                .replacen(&format!(" {}", REPLAY_VERSION), " 2", 1);
+        assert!(matches!(Replay::parse(&text),
+                Err(ReplayError::UnsupportedVersion(2))));
+    }
+
+}
@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+use std::io::{self, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::thread;
+use std::time::{Duration, Instant};
+use rand::{thread_rng, Rng};
+use crate::config::{Config, MAX_PLAYERS};
+use crate::game::Game;
+use crate::net::{self, ClientMessage, ServerMessage, Seat, SeatState, Snapshot};
+
+
+/// Server updates per second unless told otherwise.
+pub const TICK_RATE: u32 = 100;
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+// messages waiting for a slow client before it is dropped
+const OUTBOX_SIZE: usize = 64;
+
+
+/// Runs games of several snakes for clients connected over TCP. The
+/// server owns the only `Game` that is ever ticked; clients send turns
+/// and render the snapshots they get back after every tick.
+pub struct Server {
+    config: Config,
+    max_players: usize,
+    tick_rate: u32,
+    events: Receiver<Event>,
+    clients: HashMap<usize, Client>,
+    seats: Vec<SeatInfo>,
+    game: Option<Game>,
+}
+
+enum Event {
+    Connected(usize, SyncSender<String>),
+    Message(usize, ClientMessage),
+    Disconnected(usize),
+}
+
+// every client is written to on a thread of its own, so a slow one never
+// holds up the game
+struct Client {
+    outbox: SyncSender<String>,
+    seat: Option<usize>,
+}
+
+struct SeatInfo {
+    name: String,
+    token: u64,
+    ready: bool,
+    client: Option<usize>,
+}
+
+
+impl Server {
+
+    /// Starts listening on `address`. Every game is built from `config`
+    /// with one snake per seated player.
+    pub fn bind<A: ToSocketAddrs>(address: A, config: Config)
+            -> io::Result<Server> {
+        let listener = TcpListener::bind(address)?;
+        let (sender, events) = mpsc::channel();
+        thread::spawn(move || accept(listener, sender));
+        Ok(Server {
+            config,
+            max_players: usize::from(MAX_PLAYERS),
+            tick_rate: TICK_RATE,
+            events,
+            clients: HashMap::new(),
+            seats: Vec::new(),
+            game: None,
+        })
+    }
+
+    pub fn with_max_players(mut self, max_players: usize) -> Server {
+        self.max_players = max_players;
+        self
+    }
+
+    /// How many times a second the server checks for messages and
+    /// advances the game.
+    pub fn with_tick_rate(mut self, tick_rate: u32) -> Server {
+        self.tick_rate = tick_rate;
+        self
+    }
+
+    /// Serves forever.
+    pub fn run(&mut self) {
+        let frame = Duration::from_secs(1) / self.tick_rate.max(1);
+        let mut last_update = Instant::now();
+        loop {
+            while let Ok(event) = self.events.try_recv() {
+                self.handle(event);
+            }
+            let now = Instant::now();
+            self.update(now.duration_since(last_update).as_secs_f64());
+            last_update = now;
+            thread::sleep(frame.saturating_sub(last_update.elapsed()));
+        }
+    }
+
+    fn handle(&mut self, event: Event) {
+        match event {
+            Event::Connected(client, outbox) => {
+                self.clients.insert(client, Client { outbox, seat: None });
+            },
+            Event::Message(client, ClientMessage::Hello { version, name, token }) => {
+                self.greet(client, version, &name, token);
+            },
+            Event::Message(client, ClientMessage::Ready(ready)) => {
+                let seat = self.clients.get(&client).and_then(|c| c.seat);
+                if let (Some(seat), None) = (seat, &self.game) {
+                    self.seats[seat].ready = ready;
+                    self.broadcast_lobby();
+                    self.start_when_ready();
+                }
+            },
+            Event::Message(client, ClientMessage::Turn(direction)) => {
+                let seat = self.clients.get(&client).and_then(|c| c.seat);
+                if let (Some(seat), Some(game)) = (seat, self.game.as_mut()) {
+                    game.turn_snake(seat, direction);
+                }
+            },
+            Event::Message(client, ClientMessage::Bye)
+                    | Event::Disconnected(client) => self.drop_client(client),
+        }
+    }
+
+    fn greet(&mut self, client: usize, version: u32, name: &str,
+            token: Option<u64>) {
+        // a client says hello once; a second one must not take another seat
+        match self.clients.get(&client) {
+            Some(connection) if connection.seat.is_none() => (),
+            _ => return,
+        }
+        if version != net::PROTOCOL_VERSION {
+            self.refuse(client, &format!(
+                    "the server speaks protocol version {}", net::PROTOCOL_VERSION));
+            return;
+        }
+        let away_seat = token.and_then(|token| self.seats.iter()
+                .position(|seat| seat.token == token && seat.client.is_none()));
+        let seat = match away_seat {
+            Some(seat) => seat,
+            None if self.game.is_some() => {
+                self.refuse(client, "a game is in progress");
+                return;
+            },
+            None if self.seats.len() >= self.max_players => {
+                self.refuse(client, "the lobby is full");
+                return;
+            },
+            None => {
+                self.seats.push(SeatInfo {
+                    name: net::clean_name(name),
+                    token: thread_rng().gen(),
+                    ready: false,
+                    client: None,
+                });
+                self.seats.len() - 1
+            },
+        };
+        self.seats[seat].client = Some(client);
+        if let Some(connection) = self.clients.get_mut(&client) {
+            connection.seat = Some(seat);
+        }
+        let token = self.seats[seat].token;
+        self.send(client, &ServerMessage::Welcome { token });
+        if let Some(game) = &self.game {
+            // back from a lost connection, in the middle of the game
+            let state = ServerMessage::State(Snapshot::capture(game));
+            self.send(client, &ServerMessage::Config(Box::new(self.game_config())));
+            self.send(client, &ServerMessage::Start { player: seat });
+            self.send(client, &state);
+        }
+        self.broadcast_lobby();
+    }
+
+    fn refuse(&mut self, client: usize, reason: &str) {
+        self.send(client, &ServerMessage::Refused(String::from(reason)));
+        self.drop_client(client);
+    }
+
+    // the writer hangs up once it sent what is left in the outbox
+    fn drop_client(&mut self, client: usize) {
+        let connection = match self.clients.remove(&client) {
+            Some(connection) => connection,
+            None => return,
+        };
+        if let Some(seat) = connection.seat {
+            if self.game.is_some() {
+                // keep the seat so the player can reconnect
+                self.seats[seat].client = None;
+            } else {
+                self.remove_seat(seat);
+            }
+            self.broadcast_lobby();
+        }
+    }
+
+    fn remove_seat(&mut self, seat: usize) {
+        self.seats.remove(seat);
+        for connection in self.clients.values_mut() {
+            if let Some(other) = connection.seat.filter(|other| *other > seat) {
+                connection.seat = Some(other - 1);
+            }
+        }
+    }
+
+    fn start_when_ready(&mut self) {
+        let ready = !self.seats.is_empty() && self.seats.iter()
+                .all(|seat| seat.ready && seat.client.is_some());
+        if !ready {
+            return;
+        }
+        let config = self.game_config();
+        if let Err(e) = config.validate() {
+            eprintln!("Cannot start a game of {} players: {}", self.seats.len(), e);
+            for seat in self.seats.iter_mut() {
+                seat.ready = false;
+            }
+            self.broadcast_lobby();
+            return;
+        }
+        let game = config.build_game();
+        self.broadcast(&ServerMessage::Config(Box::new(config)));
+        let players: Vec<(usize, usize)> = self.seats.iter().enumerate()
+                .filter_map(|(seat, info)| info.client.map(|client| (client, seat)))
+                .collect();
+        for (client, player) in players {
+            self.send(client, &ServerMessage::Start { player });
+        }
+        self.broadcast(&ServerMessage::State(Snapshot::capture(&game)));
+        self.game = Some(game);
+        self.broadcast_lobby();
+    }
+
+    fn update(&mut self, delta_time: f64) {
+        let game = match self.game.as_mut() {
+            Some(game) => game,
+            None => return,
+        };
+        let ticks = game.get_ticks();
+        game.update(delta_time);
+        if game.get_ticks() == ticks {
+            return;
+        }
+        let state = ServerMessage::State(Snapshot::capture(game));
+        let finished = game.is_finished();
+        self.broadcast(&state);
+        if finished {
+            self.game = None;
+            // players who never came back leave with the game
+            for seat in (0..self.seats.len()).rev() {
+                if self.seats[seat].client.is_none() {
+                    self.remove_seat(seat);
+                }
+            }
+            for seat in self.seats.iter_mut() {
+                seat.ready = false;
+            }
+            self.broadcast_lobby();
+        }
+    }
+
+    // the configured board with a snake for every seat
+    fn game_config(&self) -> Config {
+        let mut config = self.config.clone();
+        config.set_player_count(self.seats.len());
+        config
+    }
+
+    fn broadcast_lobby(&mut self) {
+        let playing = self.game.is_some();
+        let seats = self.seats.iter()
+                .map(|seat| Seat {
+                    name: seat.name.clone(),
+                    state: match (playing, seat.client, seat.ready) {
+                        (true, Some(_), _) => SeatState::Playing,
+                        (true, None, _) => SeatState::Away,
+                        (false, _, true) => SeatState::Ready,
+                        (false, _, false) => SeatState::Waiting,
+                    },
+                })
+                .collect();
+        self.broadcast(&ServerMessage::Lobby(seats));
+    }
+
+    fn broadcast(&mut self, message: &ServerMessage) {
+        let clients: Vec<usize> = self.clients.iter()
+                .filter(|(_, connection)| connection.seat.is_some())
+                .map(|(client, _)| *client)
+                .collect();
+        let text = message.to_string();
+        for client in clients {
+            self.deliver(client, text.clone());
+        }
+    }
+
+    fn send(&mut self, client: usize, message: &ServerMessage) {
+        self.deliver(client, message.to_string());
+    }
+
+    // queues `text` for the client, dropping it when it falls too far
+    // behind
+    fn deliver(&mut self, client: usize, text: String) {
+        let failed = match self.clients.get(&client) {
+            Some(connection) => connection.outbox.try_send(text).is_err(),
+            None => false,
+        };
+        if failed {
+            self.drop_client(client);
+        }
+    }
+
+}
+
+
+
+// hands every new connection to the server, reads its messages on a
+// thread of its own and writes to it on another
+fn accept(listener: TcpListener, events: Sender<Event>) {
+    for (client, stream) in listener.incoming().enumerate() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to accept a connection: {}", e);
+                continue;
+            },
+        };
+        let reader = match prepare(&stream) {
+            Ok(reader) => reader,
+            Err(e) => {
+                eprintln!("Failed to set up a connection: {}", e);
+                continue;
+            },
+        };
+        let (outbox, messages) = mpsc::sync_channel(OUTBOX_SIZE);
+        thread::spawn(move || write(stream, messages));
+        if events.send(Event::Connected(client, outbox)).is_err() {
+            return;
+        }
+        let events = events.clone();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            loop {
+                match ClientMessage::read(&mut reader) {
+                    Ok(Some(message)) => {
+                        if events.send(Event::Message(client, message)).is_err() {
+                            return;
+                        }
+                    },
+                    Ok(None) => break,
+                    Err(e) => {
+                        eprintln!("Dropping client {}: {}", client, e);
+                        break;
+                    },
+                }
+            }
+            let _ = events.send(Event::Disconnected(client));
+        });
+    }
+}
+
+// writes until the server drops the client or the client stops reading;
+// hanging up also ends the reading thread
+fn write(mut stream: TcpStream, messages: Receiver<String>) {
+    for message in messages.iter() {
+        if stream.write_all(message.as_bytes()).is_err() {
+            break;
+        }
+    }
+    let _ = stream.shutdown(Shutdown::Both);
+}
+
+fn prepare(stream: &TcpStream) -> io::Result<TcpStream> {
+    stream.set_nodelay(true)?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    stream.try_clone()
+}
@@ -1,16 +1,10 @@
-use std::collections::LinkedList;
-use piston_window::types::Color;
-use piston_window::{GfxFactory, Context, G2d};
+use std::collections::{LinkedList, VecDeque};
 use crate::playground::Playground;
-use crate::draw::{Block, Shape, Position, Direction, draw_eyes};
+use crate::config::SnakeConfig;
+use crate::grid::{Block, Shape, Position, Direction};
 
 
-const SNAKE_COLOR: Color = [0.19, 0.19, 0.18, 1.0];
-const SNAKE_BODY_SHAPE: Shape = Shape::Square(SNAKE_COLOR);
-const SNAKE_HEAD_SHAPE: Shape = Shape::Circle(SNAKE_COLOR);
-const SNAKE_INIT_POSITION: Position = Position (5, 3);
-const SNAKE_INIT_DIRECTION: Direction = Direction::Right;
-const SNAKE_INIT_LENGTH: u8 = 2;
+pub(crate) const SNAKE_TURN_BUFFER: usize = 3;
 
 
 pub struct Snake {
@@ -18,8 +12,10 @@ pub struct Snake {
     body_shape: Shape,
     body: LinkedList<Block>,
     direction: Direction,
+    turns: VecDeque<Direction>,
     init_position: Position,
     init_direction: Direction,
+    init_length: u32,
     prev_tail: Position,
     eatings: u32,
 }
@@ -28,8 +24,7 @@ pub struct Snake {
 impl Default for Snake {
 
     fn default() -> Snake {
-        let head = Block::new(SNAKE_INIT_POSITION, SNAKE_HEAD_SHAPE);
-        Snake::new(head, SNAKE_BODY_SHAPE, SNAKE_INIT_DIRECTION)
+        SnakeConfig::default().build()
     }
 
 }
@@ -38,12 +33,13 @@ impl Default for Snake {
 impl Snake {
 
     pub fn new(head: Block, body_shape: Shape, 
-            init_direction: Direction) -> Snake {
+            init_direction: Direction, init_length: u32) -> Snake {
         let init_position = head.get_position().clone();
         let body: LinkedList<Block> = Snake::build_snake_body(
             &body_shape,
             &init_position,
-            &init_direction);
+            &init_direction,
+            init_length);
         let tail_pos = body.back().unwrap().get_position().clone();
         
         Snake {
@@ -51,51 +47,54 @@ impl Snake {
             body_shape,
             body,
             direction: init_direction.clone(),
+            turns: VecDeque::with_capacity(SNAKE_TURN_BUFFER),
             init_direction,
             init_position,
+            init_length,
             prev_tail: tail_pos,
             eatings: 0,
         }
     }
 
     fn build_snake_body(body_shape: &Shape, init_position: &Position,
-            init_direction: &Direction) -> LinkedList<Block> {
+            init_direction: &Direction, init_length: u32)
+            -> LinkedList<Block> {
         let mut body: LinkedList<Block> = LinkedList::new();
-        let mut pos = init_position.clone();
-        for _ in 0..SNAKE_INIT_LENGTH {
-            pos = match init_direction {
-                Direction::Up => pos.shifted_by(0, 1),
-                Direction::Down => pos.shifted_by(0, -1),
-                Direction::Left => pos.shifted_by(1, 0),
-                Direction::Right => pos.shifted_by(-1, 0),
-            };
-            let body_block = Block::new(pos.clone(), body_shape.clone());
+        let Position (column, row) = *init_position;
+        let (columns, rows) = init_direction.opposite().offset();
+        for distance in 1..=init_length as i32 {
+            let pos = Position::new(
+                column.saturating_add_signed(columns * distance),
+                row.saturating_add_signed(rows * distance));
+            let body_block = Block::new(pos, body_shape.clone());
             body.push_back(body_block);
         }
         body
     }
 
-    pub fn draw(&self, factory: &mut GfxFactory, context: &Context, 
-                graphics: &mut G2d) {
-        for block in self.body.iter() {
-            block.draw(factory, context, graphics);
+    /// Buffers a turn to be taken on a later step. The turn is checked
+    /// against the last buffered direction rather than the current one,
+    /// so quick sequences like Up then Left both land. Returns `false` if
+    /// the turn is redundant, a U-turn or the buffer is full.
+    pub fn queue_turn(&mut self, direction: Direction) -> bool {
+        let last_direction = self.turns.back().unwrap_or(&self.direction);
+        if direction == *last_direction
+                || direction == last_direction.opposite()
+                || self.turns.len() >= SNAKE_TURN_BUFFER {
+            return false;
         }
-        self.head.draw(factory, context, graphics);
-        draw_eyes(&self.head, &self.direction, context, graphics);
+        self.turns.push_back(direction);
+        true
     }
 
-    pub fn step(&mut self, dir: Option<Direction>) {
-        if let Some(direction) = dir {
+    pub fn step(&mut self, playground: &Playground) {
+        if let Some(direction) = self.turns.pop_front() {
             self.direction = direction;
         }
 
         let last_head_pos = self.head.get_position().clone();
-        let new_head_pos = match self.direction {
-            Direction::Up => last_head_pos.shifted_by(0, -1),
-            Direction::Down => last_head_pos.shifted_by(0, 1),
-            Direction::Left => last_head_pos.shifted_by(-1, 0),
-            Direction::Right => last_head_pos.shifted_by(1, 0),
-        };
+        let new_head_pos = playground.next_position(
+                &last_head_pos, &self.direction);
         self.head.set_position(new_head_pos);
         let mut new_block = self.body.pop_back().unwrap();
         self.prev_tail = new_block.get_position().clone();
@@ -116,15 +115,30 @@ impl Snake {
         self.body = Snake::build_snake_body(
             &self.body_shape,
             &self.init_position,
-            &self.init_direction);
+            &self.init_direction,
+            self.init_length);
         self.direction = self.init_direction.clone();
+        self.turns.clear();
         self.prev_tail = self.body.back().unwrap()
                 .get_position().clone();
         self.eatings = 0;
     }
 
+    /// Lays the snake out on `blocks`, head first, as received from a
+    /// server. Buffered turns are dropped.
+    pub fn restore(&mut self, blocks: &[Position], direction: Direction) {
+        if let Some((head, body)) = blocks.split_first() {
+            self.head.set_position(head.clone());
+            self.body = body.iter()
+                    .map(|position| Block::new(position.clone(), self.body_shape.clone()))
+                    .collect();
+        }
+        self.direction = direction;
+        self.turns.clear();
+    }
+
     pub fn worth_bonus(&self) -> bool {
-        self.eatings != 0 && self.eatings%5 == 0
+        self.eatings != 0 && self.eatings % 5 == 0
     }
 
     pub fn bite_itself(&self) -> bool {
@@ -137,17 +151,7 @@ impl Snake {
     }
 
     pub fn hit_walls_of(&self, playground: &Playground) -> bool {
-        let width = playground.get_width();
-        let height = playground.get_height();
-        let wall_width = playground.get_border_width();
-        let Position (column, row) = *self.get_head_position();
-
-        let hit_top = row == (wall_width - 1);
-        let hit_left = column == (wall_width - 1);
-        let hit_bottom = row == (height - wall_width);
-        let hit_right = column == (width - wall_width);
-
-        hit_top || hit_bottom || hit_left || hit_right
+        playground.is_wall(self.get_head_position())
     }
 
     pub fn on_position(&self, position: &Position) -> bool {
@@ -159,6 +163,14 @@ impl Snake {
         self.get_head_position() == position
     }
 
+    pub fn get_head(&self) -> &Block {
+        &self.head
+    }
+
+    pub fn get_body(&self) -> impl Iterator<Item = &Block> {
+        self.body.iter()
+    }
+
     pub fn get_head_position(&self) -> &Position {
         self.head.get_position()
     }
@@ -167,6 +179,35 @@ impl Snake {
         &self.direction
     }
 
+    /// The direction the snake goes once its buffered turns are taken,
+    /// which relative turns are made from.
+    pub fn get_heading(&self) -> &Direction {
+        self.turns.back().unwrap_or(&self.direction)
+    }
+
+    /// Where the tail was before the last step. It stays put when the
+    /// snake grew.
+    pub fn get_prev_tail(&self) -> &Position {
+        &self.prev_tail
+    }
+
+    pub fn get_init_position(&self) -> &Position {
+        &self.init_position
+    }
+
+    pub fn get_init_direction(&self) -> &Direction {
+        &self.init_direction
+    }
+
+    pub fn get_init_length(&self) -> u32 {
+        self.init_length
+    }
+
+    /// Blocks of snake, head included.
+    pub fn get_length(&self) -> u32 {
+        self.body.len() as u32 + 1
+    }
+
     pub fn get_eatings(&self) -> u32 {
         self.eatings
     }
@@ -0,0 +1,255 @@
+use std::io::{self, Stdout, Write};
+use crossterm::{cursor, execute, queue, terminal};
+use crossterm::style::{self, Print, SetBackgroundColor, SetForegroundColor};
+use crate::grid::{Position, Direction, Color};
+use crate::game::{Game, Status};
+use crate::playground::Playground;
+use crate::snake::Snake;
+use crate::theme::Theme;
+
+
+// a block takes two characters, as terminal cells are about twice as high
+// as they are wide
+const BLOCK_WIDTH: u16 = 2;
+const HUD_HEIGHT: u16 = 2;
+const BANNER_COLOR: Color = [0.0, 0.0, 0.0, 1.0];
+const FOOD_COLOR: Color = [0.13, 0.45, 0.13, 1.0];
+const BONUS_COLOR: Color = [0.45, 0.35, 0.30, 1.0];
+const FOOD_GLYPH: &str = "()";
+const BONUS_GLYPH: &str = "<>";
+
+
+/// The terminal the game is played in. Opening it switches to the
+/// alternate screen with raw input and a hidden cursor; dropping it puts
+/// the terminal back the way it was.
+pub struct Screen {
+    out: Stdout,
+}
+
+// a screenful of characters with their colors, laid out before any of
+// them is printed
+struct Frame {
+    width: usize,
+    text_color: Color,
+    cells: Vec<(char, Color, Color)>,
+}
+
+
+impl Screen {
+
+    pub fn open() -> io::Result<Screen> {
+        let mut out = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(out, terminal::EnterAlternateScreen, cursor::Hide,
+                terminal::Clear(terminal::ClearType::All))?;
+        Ok(Screen { out })
+    }
+
+    /// Draws `game` in the colors of `theme` with its score line, a
+    /// banner when it is paused or over, and the `lines` of text under the
+    /// banner.
+    pub fn draw(&mut self, game: &Game, theme: &Theme, lines: &[&str])
+            -> io::Result<()> {
+        let playground = game.get_playground();
+        let (columns, rows) = terminal::size()?;
+        let (width, height) = screen_size(playground);
+        if columns < width || rows < height {
+            return self.draw_message(&format!(
+                    "Enlarge the terminal to {}x{} to play", width, height));
+        }
+        let frame = Frame::of(game, theme, lines);
+        queue!(self.out, cursor::MoveTo(0, 0))?;
+        let mut colors = None;
+        for (row, line) in frame.cells.chunks(frame.width).enumerate() {
+            queue!(self.out, cursor::MoveTo(0, row as u16))?;
+            for (character, foreground, background) in line {
+                if colors != Some((*foreground, *background)) {
+                    colors = Some((*foreground, *background));
+                    queue!(self.out,
+                            SetForegroundColor(to_terminal_color(*foreground)),
+                            SetBackgroundColor(to_terminal_color(*background)))?;
+                }
+                queue!(self.out, Print(character))?;
+            }
+        }
+        queue!(self.out, style::ResetColor)?;
+        self.out.flush()
+    }
+
+    /// Replaces whatever is on screen with `message`.
+    pub fn draw_message(&mut self, message: &str) -> io::Result<()> {
+        queue!(self.out, style::ResetColor,
+                terminal::Clear(terminal::ClearType::All),
+                cursor::MoveTo(0, 0), Print(message))?;
+        self.out.flush()
+    }
+
+    /// Wipes the screen, e.g. after the terminal was resized.
+    pub fn clear(&mut self) -> io::Result<()> {
+        queue!(self.out, style::ResetColor,
+                terminal::Clear(terminal::ClearType::All))?;
+        self.out.flush()
+    }
+
+}
+
+
+impl Drop for Screen {
+
+    fn drop(&mut self) {
+        let _ = execute!(self.out, style::ResetColor, cursor::Show,
+                terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+
+}
+
+
+impl Frame {
+
+    fn of(game: &Game, theme: &Theme, lines: &[&str]) -> Frame {
+        let playground = game.get_playground();
+        let (width, height) = screen_size(playground);
+        let text_color = theme.get_text_color();
+        let board_color = theme.get_playground_color(playground);
+        let mut frame = Frame {
+            width: usize::from(width),
+            text_color,
+            cells: vec![(' ', text_color, theme.get_border_color(playground));
+                    usize::from(width) * usize::from(height)],
+        };
+        frame.put_playground(playground, theme);
+        frame.put_block(game.get_food().get_position(), FOOD_GLYPH,
+                FOOD_COLOR, board_color);
+        if let Some(bonus) = game.get_bonus() {
+            frame.put_block(bonus.get_position(), BONUS_GLYPH,
+                    BONUS_COLOR, board_color);
+        }
+        for (player, snake) in game.get_snakes().iter().enumerate() {
+            if game.is_alive(player) {
+                frame.put_snake(snake, theme.get_snake_color(player, snake));
+            }
+        }
+        frame.put_hud(game, theme);
+        frame.put_banner(game, lines);
+        frame
+    }
+
+    fn put_playground(&mut self, playground: &Playground, theme: &Theme) {
+        for row in 0..playground.get_height() {
+            for column in 0..playground.get_width() {
+                let position = Position::new(column, row);
+                // a dashed border shows the snake can pass through
+                let border = playground.in_border(&position)
+                        && (!playground.is_wrapping() || (column + row) % 2 == 0);
+                let color = if border || playground.is_wall(&position) {
+                    theme.get_border_color(playground)
+                } else {
+                    theme.get_playground_color(playground)
+                };
+                self.put_block(&position, "  ", self.text_color, color);
+            }
+        }
+    }
+
+    fn put_snake(&mut self, snake: &Snake, color: Color) {
+        for block in snake.get_body() {
+            self.put_block(block.get_position(), "  ", self.text_color, color);
+        }
+        let eyes = match snake.get_direction() {
+            Direction::Up => "''",
+            Direction::Down => "..",
+            Direction::Left => ": ",
+            Direction::Right => " :",
+        };
+        self.put_block(snake.get_head_position(), eyes, self.text_color, color);
+    }
+
+    // the scores along the bottom lines, like the window shows them
+    fn put_hud(&mut self, game: &Game, theme: &Theme) {
+        let row = game.get_playground().get_height() as usize;
+        let background = theme.get_border_color(game.get_playground());
+        let text_color = self.text_color;
+        if game.get_player_count() == 1 {
+            self.put_text(row, 2, &format!("Score: {}", game.get_score()),
+                    text_color, background);
+        } else {
+            let players = game.get_snakes().iter().zip(game.get_scores());
+            for (player, (snake, score)) in players.enumerate() {
+                let column = 2 + 16 * player;
+                let color = theme.get_snake_color(player, snake);
+                self.put_text(row, column, "  ", text_color, color);
+                self.put_text(row, column + 3, &format!("P{}: {}", player + 1, score),
+                        text_color, background);
+            }
+        }
+        if let (Some(target), 1) = (game.get_target(), game.get_player_count()) {
+            self.put_text(row, 20, &format!("Goal: {} {}/{}", target.get_label(),
+                    target.progress(game), target.get_goal()),
+                    text_color, background);
+        }
+    }
+
+    // the state of a paused or finished game across the middle of the
+    // board, with the lines under it
+    fn put_banner(&mut self, game: &Game, lines: &[&str]) {
+        let banner = match game.get_status() {
+            Status::Running => return,
+            Status::Paused => String::from("Paused"),
+            Status::GameOver => match (game.get_winner(), game.get_player_count()) {
+                (_, 1) => String::from("Game Over"),
+                (Some(player), _) => format!("Player {} Wins", player + 1),
+                (None, _) => String::from("Draw"),
+            },
+            Status::Cleared => String::from("Stage Cleared"),
+        };
+        let middle = game.get_playground().get_height() as usize / 2;
+        let top = middle.saturating_sub(lines.len() / 2 + 1);
+        let texts = Some(banner.as_str()).into_iter()
+                .chain(lines.iter().cloned());
+        for (row, text) in (top..).zip(texts) {
+            let padded = format!(" {} ", text);
+            let length = padded.chars().count();
+            let column = self.width.saturating_sub(length) / 2;
+            self.put_text(row, column, &padded, self.text_color, BANNER_COLOR);
+        }
+    }
+
+    fn put_block(&mut self, position: &Position, glyph: &str,
+            foreground: Color, background: Color) {
+        let Position (column, row) = *position;
+        self.put_text(row as usize, column as usize * usize::from(BLOCK_WIDTH),
+                glyph, foreground, background);
+    }
+
+    fn put_text(&mut self, row: usize, column: usize, text: &str,
+            foreground: Color, background: Color) {
+        let height = self.cells.len() / self.width;
+        if row >= height {
+            return;
+        }
+        for (column, character) in (column..self.width).zip(text.chars()) {
+            self.cells[row * self.width + column] = (character, foreground, background);
+        }
+    }
+
+}
+
+
+
+/// The characters across and lines down the game of `playground` takes,
+/// score line included.
+pub fn screen_size(playground: &Playground) -> (u16, u16) {
+    let width = playground.get_width() as u16 * BLOCK_WIDTH;
+    let height = playground.get_height() as u16 + HUD_HEIGHT;
+    (width, height)
+}
+
+fn to_terminal_color(color: Color) -> style::Color {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    style::Color::Rgb {
+        r: channel(color[0]),
+        g: channel(color[1]),
+        b: channel(color[2]),
+    }
+}
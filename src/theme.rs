@@ -0,0 +1,274 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
+use crate::config::Config;
+use crate::grid::Color;
+use crate::playground::Playground;
+use crate::snake::Snake;
+
+
+const BUILTIN_THEMES: [(&str, &str); 2] = [
+    ("classic", include_str!("../themes/classic.toml")),
+    ("night", include_str!("../themes/night.toml")),
+];
+// compiled in, so the game runs from any directory
+const EMBEDDED_FILES: [(&str, &[u8]); 3] = [
+    ("ExoExtraBold.ttf", include_bytes!("../resources/ExoExtraBold.ttf")),
+    ("Frog.png", include_bytes!("../resources/Frog.png")),
+    ("Mouse.png", include_bytes!("../resources/Mouse.png")),
+];
+const CLASSIC_THEME: &str = "classic";
+const THEMES_FOLDER: &str = "themes";
+const MANIFEST_FILE: &str = "theme.toml";
+const DEFAULT_FONT: &str = "ExoExtraBold.ttf";
+const DEFAULT_FOOD: &str = "Frog.png";
+const DEFAULT_BONUS: &str = "Mouse.png";
+const DEFAULT_TEXT_COLOR: Color = [1.0, 1.0, 1.0, 1.0];
+
+
+/// How the game looks: the font, the sprites and the colors, read from a
+/// TOML manifest. Files are looked up next to the manifest first, then
+/// among the files built into the game, so a theme pack only has to ship
+/// what it changes.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// Shown when switching themes, the manifest's folder or file name
+    /// when left out
+    pub name: String,
+    /// A TrueType font file
+    pub font: String,
+    pub sprites: Sprites,
+    pub colors: ThemeColors,
+    #[serde(skip)]
+    folder: Option<PathBuf>,
+}
+
+/// PNG files for the things on the board. Snake sprites are tinted with
+/// the snake's color, so they are best white, and are turned as the
+/// snake goes: the head faces up, the body runs from top to bottom, the
+/// corner joins the bottom edge to the right one and the tail meets the
+/// body at its top edge. Without a body sprite the snake is drawn as a
+/// plain band.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sprites {
+    pub food: String,
+    pub bonus: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub head: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub corner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tail: Option<String>,
+}
+
+/// Colors replacing the ones of the config file, where set.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeColors {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub playground: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<Color>,
+    /// One per player, from the first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub snakes: Vec<Color>,
+}
+
+#[derive(Debug)]
+pub enum ThemeError {
+    Io(io::Error),
+    Parse(String),
+    /// A file the theme names is neither next to it nor built in
+    Missing(String),
+}
+
+
+impl Default for Theme {
+
+    fn default() -> Theme {
+        Theme {
+            name: String::new(),
+            font: String::from(DEFAULT_FONT),
+            sprites: Sprites::default(),
+            colors: ThemeColors::default(),
+            folder: None,
+        }
+    }
+
+}
+
+
+impl Default for Sprites {
+
+    fn default() -> Sprites {
+        Sprites {
+            food: String::from(DEFAULT_FOOD),
+            bonus: String::from(DEFAULT_BONUS),
+            head: None,
+            body: None,
+            corner: None,
+            tail: None,
+        }
+    }
+
+}
+
+
+impl Theme {
+
+    /// The look the game has without a theme set.
+    pub fn classic() -> Theme {
+        Theme::find(CLASSIC_THEME).expect("the classic theme is built in")
+    }
+
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTIN_THEMES.iter().map(|(name, _)| *name)
+    }
+
+    /// The built-in themes, then the theme packs installed in the game's
+    /// data folder, each a folder with a `theme.toml` manifest.
+    pub fn names() -> Vec<String> {
+        let mut names: Vec<String> = Theme::builtin_names()
+                .map(String::from)
+                .collect();
+        let packs = Config::data_path(THEMES_FOLDER)
+                .and_then(|folder| fs::read_dir(folder).ok());
+        if let Some(packs) = packs {
+            let mut installed: Vec<String> = packs
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.path().join(MANIFEST_FILE).is_file())
+                    .filter_map(|entry| entry.file_name().into_string().ok())
+                    .collect();
+            installed.sort();
+            names.extend(installed);
+        }
+        names
+    }
+
+    /// Finds a built-in theme or an installed theme pack by name, or else
+    /// loads `name` as a manifest or a folder holding one.
+    pub fn find(name: &str) -> Result<Theme, ThemeError> {
+        if let Some((_, text)) = BUILTIN_THEMES.iter()
+                .find(|(builtin, _)| *builtin == name) {
+            return Theme::parse(text);
+        }
+        let installed = Config::data_path(THEMES_FOLDER)
+                .map(|folder| folder.join(name))
+                .filter(|pack| pack.join(MANIFEST_FILE).is_file());
+        match installed {
+            Some(pack) => Theme::load(pack),
+            None => Theme::load(name),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Theme, ThemeError> {
+        let path = path.as_ref();
+        let manifest = if path.is_dir() {
+            path.join(MANIFEST_FILE)
+        } else {
+            path.to_path_buf()
+        };
+        let mut theme = Theme::parse(&fs::read_to_string(&manifest)?)?;
+        if theme.name.is_empty() {
+            let named = if path.is_dir() { path } else { &manifest };
+            theme.name = named.file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+        }
+        theme.folder = manifest.parent().map(Path::to_path_buf);
+        Ok(theme)
+    }
+
+    pub fn parse(text: &str) -> Result<Theme, ThemeError> {
+        toml::from_str(text).map_err(|e| ThemeError::Parse(e.to_string()))
+    }
+
+    /// The contents of `file`, from the theme's folder or else built in.
+    pub fn read_file(&self, file: &str) -> Result<Cow<'static, [u8]>, ThemeError> {
+        if let Some(path) = self.get_file_path(file) {
+            return Ok(Cow::Owned(fs::read(path)?));
+        }
+        Theme::get_embedded_file(file)
+                .map(Cow::Borrowed)
+                .ok_or_else(|| ThemeError::Missing(String::from(file)))
+    }
+
+    /// Where `file` is in the theme's folder, if the theme has a folder
+    /// and the file is in it.
+    pub fn get_file_path(&self, file: &str) -> Option<PathBuf> {
+        self.folder.as_ref()
+                .map(|folder| folder.join(file))
+                .filter(|path| path.is_file())
+    }
+
+    pub fn get_embedded_file(file: &str) -> Option<&'static [u8]> {
+        EMBEDDED_FILES.iter()
+                .find(|(name, _)| *name == file)
+                .map(|(_, bytes)| *bytes)
+    }
+
+    /// The name of the theme after this one among `Theme::names`, the
+    /// first one when this one is not among them.
+    pub fn get_next_name(&self) -> String {
+        let mut names = Theme::names();
+        let current = names.iter()
+                .position(|name| Theme::find(name)
+                        .is_ok_and(|theme| theme.name == self.name));
+        let next = current.map_or(0, |index| (index + 1) % names.len());
+        names.swap_remove(next)
+    }
+
+    pub fn get_text_color(&self) -> Color {
+        self.colors.text.unwrap_or(DEFAULT_TEXT_COLOR)
+    }
+
+    pub fn get_playground_color(&self, playground: &Playground) -> Color {
+        self.colors.playground.unwrap_or_else(|| playground.get_color())
+    }
+
+    pub fn get_border_color(&self, playground: &Playground) -> Color {
+        self.colors.border.unwrap_or_else(|| playground.get_border_color())
+    }
+
+    /// The theme's color for the snake of `player`, else the color it was
+    /// built with.
+    pub fn get_snake_color(&self, player: usize, snake: &Snake) -> Color {
+        self.colors.snakes.get(player).cloned()
+                .unwrap_or_else(|| snake.get_head().get_shape().get_color())
+    }
+
+}
+
+
+impl fmt::Display for ThemeError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ThemeError::Io(error) => write!(f, "{}", error),
+            ThemeError::Parse(message) => write!(f, "{}", message),
+            ThemeError::Missing(file) => write!(f, "{} is missing", file),
+        }
+    }
+
+}
+
+
+impl std::error::Error for ThemeError {}
+
+
+impl From<io::Error> for ThemeError {
+
+    fn from(error: io::Error) -> ThemeError {
+        ThemeError::Io(error)
+    }
+
+}